@@ -0,0 +1,254 @@
+use std::collections::HashSet;
+
+use bevy::input::InputSystem;
+use bevy::input::gamepad::{GamepadConnection, GamepadConnectionEvent};
+use bevy::prelude::*;
+use bevy::ui::UiSystem;
+use bevy::window::PrimaryWindow;
+use serde::{Deserialize, Serialize};
+
+use crate::{camera::MainCamera, settings::Settings};
+
+// stick values below this are ignored
+const STICK_DEADZONE: f32 = 0.15;
+// stick values are rounded to this step, so replays only store actual changes
+const STICK_STEP: f32 = 0.05;
+
+/// Something the player can do, independent of the physical input it is bound to.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    Reset,
+    // plays the seed of the last run again after game over
+    Retry,
+    NextTheme,
+    // starts a run and confirms menu entries
+    Confirm,
+    // closes the jumpscare, pauses the game and leaves menus
+    Dismiss,
+}
+
+impl Action {
+    pub const ALL: [Action; 7] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Reset,
+        Action::Retry,
+        Action::NextTheme,
+        Action::Confirm,
+        Action::Dismiss,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::MoveLeft => "Move Left",
+            Action::MoveRight => "Move Right",
+            Action::Reset => "Reset",
+            Action::Retry => "Retry Seed",
+            Action::NextTheme => "Next Theme",
+            Action::Confirm => "Confirm",
+            Action::Dismiss => "Dismiss",
+        }
+    }
+}
+
+/// State of every action this frame, read from the bound inputs.
+#[derive(Resource, Default)]
+pub struct ActionInput {
+    pressed: HashSet<Action>,
+    just_pressed: HashSet<Action>,
+    // horizontal stick position from -1.0 to 1.0
+    axis: f32,
+    // world position of a held mouse button or touch
+    pointer: Option<Vec2>,
+    // mouse click or touch that started this frame
+    tapped: bool,
+}
+
+impl ActionInput {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    pub fn axis(&self) -> f32 {
+        self.axis
+    }
+
+    pub fn pointer(&self) -> Option<Vec2> {
+        self.pointer
+    }
+
+    pub fn tapped(&self) -> bool {
+        self.tapped
+    }
+
+    pub fn press(&mut self, action: Action) {
+        self.pressed.insert(action);
+    }
+
+    pub fn just_press(&mut self, action: Action) {
+        self.pressed.insert(action);
+        self.just_pressed.insert(action);
+    }
+
+    /// Forgets every input of this frame.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ActionSystem;
+
+pub struct ActionsPlugin;
+
+impl Plugin for ActionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActionInput>()
+            .add_systems(
+                PreUpdate,
+                (
+                    read_keyboard_actions,
+                    read_gamepad_actions,
+                    read_pointer_actions,
+                )
+                    .chain()
+                    .in_set(ActionSystem)
+                    .after(InputSystem)
+                    .after(UiSystem::Focus),
+            )
+            .add_systems(PreUpdate, log_gamepad_connections.after(InputSystem));
+    }
+}
+
+pub fn read_keyboard_actions(
+    kb_input: Res<ButtonInput<KeyCode>>,
+    settings: Res<Settings>,
+    mut action_input: ResMut<ActionInput>,
+) {
+    action_input.clear();
+
+    for action in Action::ALL {
+        let key = settings.key_bindings.key(action);
+        if kb_input.pressed(key) {
+            action_input.press(action);
+        }
+        if kb_input.just_pressed(key) {
+            action_input.just_press(action);
+        }
+    }
+}
+
+pub fn read_gamepad_actions(
+    gamepads: Query<&Gamepad>,
+    settings: Res<Settings>,
+    mut action_input: ResMut<ActionInput>,
+) {
+    for gamepad in gamepads.iter() {
+        for action in Action::ALL {
+            let button = settings.gamepad_bindings.button(action);
+            if gamepad.pressed(button) {
+                action_input.press(action);
+            }
+            if gamepad.just_pressed(button) {
+                action_input.just_press(action);
+            }
+        }
+
+        let stick = gamepad.left_stick().x;
+        if stick.abs() > STICK_DEADZONE && stick.abs() > action_input.axis.abs() {
+            action_input.axis = ((stick / STICK_STEP).round() * STICK_STEP).clamp(-1.0, 1.0);
+        }
+    }
+}
+
+pub fn read_pointer_actions(
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    touches: Res<Touches>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    interaction_query: Query<&Interaction>,
+    mut action_input: ResMut<ActionInput>,
+) {
+    // presses on buttons like the pause button neither steer nor tap
+    if interaction_query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+
+    action_input.tapped = mouse_input.just_pressed(MouseButton::Left) || touches.any_just_pressed();
+
+    // touches win over the mouse, the first finger steers
+    let screen_position = if let Some(touch) = touches.iter().next() {
+        Some(touch.position())
+    } else if mouse_input.pressed(MouseButton::Left) {
+        window_query
+            .single()
+            .ok()
+            .and_then(|window| window.cursor_position())
+    } else {
+        None
+    };
+
+    let (Some(screen_position), Ok((camera, camera_transform))) =
+        (screen_position, camera_query.single())
+    else {
+        return;
+    };
+
+    // rounded to whole pixels, so replays only store actual changes
+    action_input.pointer = camera
+        .viewport_to_world_2d(camera_transform, screen_position)
+        .ok()
+        .map(|position| position.round());
+}
+
+pub fn log_gamepad_connections(mut connection_events: EventReader<GamepadConnectionEvent>) {
+    for event in connection_events.read() {
+        match &event.connection {
+            GamepadConnection::Connected { name, .. } => info!("Gamepad connected: {}", name),
+            GamepadConnection::Disconnected => info!("Gamepad disconnected."),
+        }
+    }
+}
+
+/// Short name of a key as shown in hints and the controls menu, e.g. "R" instead of "KeyR".
+pub fn key_name(key: KeyCode) -> String {
+    let name = format!("{:?}", key);
+
+    if let Some(letter) = name.strip_prefix("Key") {
+        letter.to_string()
+    } else if let Some(digit) = name.strip_prefix("Digit") {
+        digit.to_string()
+    } else if let Some(direction) = name.strip_prefix("Arrow") {
+        direction.to_string()
+    } else {
+        name
+    }
+}
+
+/// Name of a gamepad button as shown in hints and the controls menu, using Xbox labels.
+pub fn button_name(button: GamepadButton) -> String {
+    match button {
+        GamepadButton::South => "A".to_string(),
+        GamepadButton::East => "B".to_string(),
+        GamepadButton::West => "X".to_string(),
+        GamepadButton::North => "Y".to_string(),
+        GamepadButton::LeftTrigger => "LB".to_string(),
+        GamepadButton::RightTrigger => "RB".to_string(),
+        GamepadButton::LeftTrigger2 => "LT".to_string(),
+        GamepadButton::RightTrigger2 => "RT".to_string(),
+        GamepadButton::DPadUp => "D-Pad Up".to_string(),
+        GamepadButton::DPadDown => "D-Pad Down".to_string(),
+        GamepadButton::DPadLeft => "D-Pad Left".to_string(),
+        GamepadButton::DPadRight => "D-Pad Right".to_string(),
+        other => format!("{:?}", other),
+    }
+}
@@ -1,10 +1,50 @@
+use bevy::audio::Volume;
 use bevy::prelude::*;
+use bevy_kira_audio::{Audio, AudioControl, AudioPlugin};
 
-use crate::themes::{JustLoadedTheme, Theme, ThemeHandle};
+use crate::{
+    themes::{JustLoadedTheme, Theme, ThemeHandle},
+    zones::{CurrentZone, DepthZones},
+};
+
+// seconds the music fades out before the next track starts
+const MUSIC_FADE: f32 = 1.5;
 
 #[derive(Component)]
 pub struct BackgroundMusic;
 
+// volume of music and sound effects, 0.0 is muted and 1.0 is full volume
+#[derive(Resource)]
+pub struct AudioVolume(pub f32);
+
+// paths are relative to themes/, empty when no music plays
+#[derive(Resource, Default)]
+pub struct MusicTrack {
+    pub playing: String,
+    // track that starts once the current one faded out
+    pub next: Option<String>,
+    // seconds left until the next track starts
+    pub fade: f32,
+}
+
+pub struct GameAudioPlugin {
+    pub volume: f32,
+}
+
+impl Plugin for GameAudioPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<AudioPlugin>() {
+            app.add_plugins(AudioPlugin);
+        }
+
+        app.insert_resource(AudioVolume(self.volume))
+            .init_resource::<MusicTrack>()
+            .add_systems(Startup, play_background_audio)
+            .add_systems(Update, (update_music, fade_music).chain())
+            .add_systems(Update, apply_volume);
+    }
+}
+
 pub fn play_background_audio(mut commands: Commands) {
     commands.spawn((
         AudioPlayer::new(Handle::<AudioSource>::default()),
@@ -13,30 +53,93 @@ pub fn play_background_audio(mut commands: Commands) {
     ));
 }
 
+/// Picks the music of the zone the player is in, or the music of the theme if the zone has none.
 pub fn update_music(
     themes: Res<Assets<Theme>>,
     theme_handle: Res<ThemeHandle>,
-    mut music_query: Query<(&mut AudioPlayer, Option<&AudioSink>), With<BackgroundMusic>>,
-    asset_server: Res<AssetServer>,
+    zones: DepthZones,
+    current_zone: Res<CurrentZone>,
+    mut track: ResMut<MusicTrack>,
     mut just_loaded: ResMut<JustLoadedTheme>,
 ) {
-    if just_loaded.0 {
-        if let Some(theme) = themes.get(&theme_handle.0) {
-            if let Ok((mut music_player, audio_sink)) = music_query.single_mut() {
-                if !theme.music_path.is_empty() {
-                    music_player.0 = asset_server.load(format!("themes/{}", &theme.music_path));
-                    if let Some(sink) = audio_sink {
-                        sink.play();
-                    }
-                    info!("Playing background music.")
-                } else {
-                    if let Some(sink) = audio_sink {
-                        sink.pause();
-                    }
-                    info!("Stopping background music playback.");
-                }
-            }
+    if !just_loaded.0 && !current_zone.is_changed() {
+        return;
+    }
+    // waits until the theme is loaded
+    let Some(theme) = themes.get(&theme_handle.0) else {
+        return;
+    };
+    just_loaded.0 = false;
+
+    let path = zones
+        .get()
+        .zones
+        .get(current_zone.0)
+        .and_then(|zone| zone.music_path.clone())
+        .unwrap_or_else(|| theme.music_path.clone());
+
+    if *track.next.as_ref().unwrap_or(&track.playing) != path {
+        track.next = Some(path);
+        track.fade = MUSIC_FADE;
+    }
+}
+
+/// Fades the current track out and starts the next one.
+pub fn fade_music(
+    mut commands: Commands,
+    mut track: ResMut<MusicTrack>,
+    mut music_query: Query<(Entity, Option<&mut AudioSink>), With<BackgroundMusic>>,
+    asset_server: Res<AssetServer>,
+    volume: Res<AudioVolume>,
+    time: Res<Time<Real>>,
+) {
+    let Some(next) = track.next.clone() else {
+        return;
+    };
+    let Ok((music, sink)) = music_query.single_mut() else {
+        return;
+    };
+
+    if let Some(mut sink) = sink
+        && track.fade > 0.0
+    {
+        track.fade = (track.fade - time.delta_secs()).max(0.0);
+        sink.set_volume(Volume::Linear(volume.0 * track.fade / MUSIC_FADE));
+        if track.fade > 0.0 {
+            return;
         }
-        just_loaded.0 = false;
     }
+
+    // a new player is only started for entities without a sink, removing it stops the old track
+    let mut music_entity = commands.entity(music);
+    music_entity.remove::<AudioSink>();
+    if !next.is_empty() {
+        music_entity.insert(AudioPlayer::new(
+            asset_server.load(format!("themes/{}", &next)),
+        ));
+        info!("Playing background music.")
+    } else {
+        music_entity.insert(AudioPlayer::new(Handle::<AudioSource>::default()));
+        info!("Stopping background music playback.");
+    }
+    track.playing = next;
+    track.next = None;
+}
+
+pub fn apply_volume(
+    volume: Res<AudioVolume>,
+    mut global_volume: ResMut<GlobalVolume>,
+    mut sink_query: Query<&mut AudioSink, With<BackgroundMusic>>,
+    audio: Res<Audio>,
+) {
+    if !volume.is_changed() {
+        return;
+    }
+
+    // global volume only applies to music that starts playing afterwards
+    global_volume.volume = Volume::Linear(volume.0);
+    for mut sink in sink_query.iter_mut() {
+        sink.set_volume(Volume::Linear(volume.0));
+    }
+    audio.set_volume(volume.0 as f64);
 }
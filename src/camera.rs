@@ -5,6 +5,15 @@ use crate::player_setup::PlayerTorso;
 #[derive(Component)]
 pub struct MainCamera;
 
+pub struct FollowCameraPlugin;
+
+impl Plugin for FollowCameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_camera)
+            .add_systems(PostUpdate, camera_follow_y);
+    }
+}
+
 // TODO: add optional pixelated look and glow
 pub fn setup_camera(mut commands: Commands) {
     commands.spawn((
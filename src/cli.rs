@@ -0,0 +1,57 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+/// A simple game about falling.
+#[derive(Parser, Debug, Clone)]
+#[command(version, about)]
+pub struct Args {
+    /// Theme to start with, see --list-themes
+    #[arg(long, value_name = "NAME")]
+    pub theme: Option<String>,
+    /// Print the available themes and exit
+    #[arg(long)]
+    pub list_themes: bool,
+    /// Character to play as, see --list-characters
+    #[arg(long, value_name = "NAME")]
+    pub character: Option<String>,
+    /// Print the available characters and exit
+    #[arg(long)]
+    pub list_characters: bool,
+    /// Use this seed for every run instead of a random one
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Window width in pixels
+    #[arg(long, value_name = "PIXELS")]
+    pub width: Option<f32>,
+    /// Window height in pixels
+    #[arg(long, value_name = "PIXELS")]
+    pub height: Option<f32>,
+    /// Start in fullscreen
+    #[arg(long)]
+    pub fullscreen: bool,
+    /// Mute music and sound effects
+    #[arg(long)]
+    pub mute: bool,
+    /// Draw the outlines of all physics colliders
+    #[arg(long)]
+    pub debug_physics: bool,
+    /// Show the frame rate
+    #[arg(long)]
+    pub fps: bool,
+    /// Skip the start screen and start a run right away
+    #[arg(long)]
+    pub play: bool,
+    /// Run without a window or audio and print the score once the run is over
+    #[arg(long)]
+    pub headless: bool,
+    /// Stop a headless run after this many seconds of game time
+    #[arg(long, value_name = "SECONDS", requires = "headless")]
+    pub time_limit: Option<f32>,
+    /// Save a replay of the headless run
+    #[arg(long, requires = "headless")]
+    pub record: bool,
+    /// Play back a replay file instead of playing
+    #[arg(long, value_name = "FILE")]
+    pub replay: Option<PathBuf>,
+}
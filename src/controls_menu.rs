@@ -0,0 +1,190 @@
+use bevy::prelude::*;
+
+use crate::{
+    actions::{Action, ActionInput, ActionSystem, button_name, key_name, read_pointer_actions},
+    menu::{MenuNavigation, MenuPressed, MenuState, despawn_menu, navigate_menu, spawn_menu},
+    settings::{GamepadBindings, KeyBindings, Settings},
+    themes::{Theme, ThemeHandle},
+};
+
+#[derive(Component, Clone, Copy)]
+pub enum ControlsAction {
+    Rebind(Action),
+    ResetDefaults,
+    Back,
+}
+
+// action that is waiting for a new key
+#[derive(Resource, Default)]
+pub struct Rebinding(pub Option<Action>);
+
+pub struct ControlsMenuPlugin;
+
+impl Plugin for ControlsMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Rebinding>()
+            .add_systems(OnEnter(MenuState::Controls), spawn_controls_menu)
+            .add_systems(
+                OnExit(MenuState::Controls),
+                (despawn_menu, cancel_rebinding),
+            )
+            .add_systems(
+                Update,
+                (
+                    handle_controls_menu,
+                    capture_rebind_key,
+                    update_controls_labels,
+                )
+                    .chain()
+                    .after(navigate_menu)
+                    .run_if(in_state(MenuState::Controls)),
+            )
+            // the next key press is the new binding, not menu input or an action
+            .configure_sets(
+                Update,
+                MenuNavigation.run_if(|rebinding: Res<Rebinding>| rebinding.0.is_none()),
+            )
+            .add_systems(
+                PreUpdate,
+                suppress_actions
+                    .after(read_pointer_actions)
+                    .in_set(ActionSystem)
+                    .run_if(|rebinding: Res<Rebinding>| rebinding.0.is_some()),
+            );
+    }
+}
+
+fn controls_label(action: ControlsAction, settings: &Settings, rebinding: &Rebinding) -> String {
+    match action {
+        ControlsAction::Rebind(action) if rebinding.0 == Some(action) => {
+            format!("{}: press a key or button...", action.name())
+        }
+        ControlsAction::Rebind(action) => format!(
+            "{}: {} / {}",
+            action.name(),
+            key_name(settings.key_bindings.key(action)),
+            button_name(settings.gamepad_bindings.button(action))
+        ),
+        ControlsAction::ResetDefaults => "Reset to Defaults".to_string(),
+        ControlsAction::Back => "Back".to_string(),
+    }
+}
+
+pub fn spawn_controls_menu(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    rebinding: Res<Rebinding>,
+    theme_handle: Res<ThemeHandle>,
+    themes: Res<Assets<Theme>>,
+) {
+    let mut text_color = Color::srgb(1.0, 1.0, 1.0);
+
+    if let Some(theme) = themes.get(&theme_handle.0) {
+        text_color = theme.text_color.to_color();
+    }
+
+    let items: Vec<(String, ControlsAction)> = Action::ALL
+        .iter()
+        .map(|action| ControlsAction::Rebind(*action))
+        .chain([ControlsAction::ResetDefaults, ControlsAction::Back])
+        .map(|action| (controls_label(action, &settings, &rebinding), action))
+        .collect();
+
+    spawn_menu(&mut commands, "Controls", &items, text_color);
+}
+
+pub fn suppress_actions(mut action_input: ResMut<ActionInput>) {
+    action_input.clear();
+}
+
+pub fn cancel_rebinding(mut rebinding: ResMut<Rebinding>) {
+    rebinding.0 = None;
+}
+
+pub fn handle_controls_menu(
+    action_input: Res<ActionInput>,
+    mut pressed_events: EventReader<MenuPressed>,
+    action_query: Query<&ControlsAction>,
+    mut rebinding: ResMut<Rebinding>,
+    mut settings: ResMut<Settings>,
+    mut next_menu: ResMut<NextState<MenuState>>,
+) {
+    if rebinding.0.is_some() {
+        return;
+    }
+
+    if action_input.just_pressed(Action::Dismiss) {
+        next_menu.set(MenuState::Settings);
+        return;
+    }
+
+    for event in pressed_events.read() {
+        match action_query.get(event.0) {
+            Ok(ControlsAction::Rebind(action)) => rebinding.0 = Some(*action),
+            Ok(ControlsAction::ResetDefaults) => {
+                settings.key_bindings = KeyBindings::default();
+                settings.gamepad_bindings = GamepadBindings::default();
+            }
+            Ok(ControlsAction::Back) => next_menu.set(MenuState::Settings),
+            Err(_) => {}
+        }
+    }
+}
+
+pub fn capture_rebind_key(
+    kb_input: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    mut rebinding: ResMut<Rebinding>,
+    mut settings: ResMut<Settings>,
+) {
+    // skips the press that started the rebinding
+    if rebinding.is_changed() {
+        return;
+    }
+    let Some(action) = rebinding.0 else {
+        return;
+    };
+
+    // an action that already used the key or button gets the old one instead
+    if let Some(key) = kb_input.get_just_pressed().next().copied() {
+        let bindings = &mut settings.key_bindings;
+        let old_key = bindings.key(action);
+        for other in Action::ALL {
+            if other != action && bindings.key(other) == key {
+                bindings.set_key(other, old_key);
+            }
+        }
+        bindings.set_key(action, key);
+        info!("Bound {} to {}.", action.name(), key_name(key));
+    } else if let Some(button) = gamepads
+        .iter()
+        .find_map(|gamepad| gamepad.get_just_pressed().next().copied())
+    {
+        let bindings = &mut settings.gamepad_bindings;
+        let old_button = bindings.button(action);
+        for other in Action::ALL {
+            if other != action && bindings.button(other) == button {
+                bindings.set_button(other, old_button);
+            }
+        }
+        bindings.set_button(action, button);
+        info!("Bound {} to {}.", action.name(), button_name(button));
+    } else {
+        return;
+    }
+
+    rebinding.0 = None;
+}
+
+pub fn update_controls_labels(
+    settings: Res<Settings>,
+    rebinding: Res<Rebinding>,
+    mut button_query: Query<(&ControlsAction, &mut Text)>,
+) {
+    for (action, mut text) in button_query.iter_mut() {
+        let label = controls_label(*action, &settings, &rebinding);
+        if text.0 != label {
+            text.0 = label;
+        }
+    }
+}
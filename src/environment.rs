@@ -1,65 +1,165 @@
+use std::collections::HashSet;
 use std::ops::RangeInclusive;
 
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
-use rand::{Rng, rngs::StdRng};
+use rand::{Rng, SeedableRng, rngs::StdRng};
 
 use crate::{
-    player_setup::PlayerTorso,
+    game_states::{GameState, NewRun},
+    hazards::{Hazard, spikes_mesh},
+    obstacles::{MovingObstacle, ObstacleCatalogs, ObstacleKind, ObstacleLook, ObstacleShape},
+    pickups::{PICKUP_RADIUS, spawn_pickup},
+    player::{PlayerData, WorldShift},
+    player_setup::{PlayerTorso, setup_player},
+    seed::{RunSeed, roll_run_seed},
+    segments::{PendingPiece, Segments},
+    shaft::{SHAFT_HALF_WIDTH, Shaft, chunk_at, chunk_center, seed_shaft},
     themes::{Theme, ThemeHandle},
+    zones::{DepthZones, depth_at, depth_at_run_y},
 };
 
 #[derive(Component)]
-pub struct Wall;
+pub struct Wall {
+    // the middle of the chunk is this many chunk heights from the start of the run
+    pub chunk: i64,
+}
 
 #[derive(Component)]
 pub struct ObstacleObject;
 
 #[derive(Resource)]
 pub struct ObstaclesData {
+    // seconds since the run started, so spawning does not depend on time spent in menus
+    pub run_time: f32,
     pub last_spawned: f32,
+    pub last_pickup: f32,
     pub rng: StdRng,
+    // rest of the current segment, from top to bottom
+    pub pending: Vec<PendingPiece>,
 }
 
-pub fn setup_environment(
+pub struct EnvironmentPlugin;
+
+impl Plugin for EnvironmentPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ObstaclesData {
+            run_time: 0.0,
+            last_spawned: 0.0,
+            last_pickup: 0.0,
+            rng: StdRng::from_entropy(),
+            pending: Vec::new(),
+        })
+        .add_systems(Startup, stream_walls.after(setup_player))
+        .init_resource::<Shaft>()
+        .add_systems(NewRun, seed_shaft.after(roll_run_seed))
+        .add_systems(NewRun, stream_walls.after(setup_player).after(seed_shaft))
+        .add_systems(NewRun, seed_obstacles.after(roll_run_seed))
+        .add_systems(
+            FixedUpdate,
+            manage_obstacles
+                .before(PhysicsSet::SyncBackend)
+                .run_if(in_state(GameState::InGame)),
+        )
+        .add_systems(
+            FixedUpdate,
+            (move_obstacles, stream_walls).before(PhysicsSet::SyncBackend),
+        );
+    }
+}
+
+// walls are kept this far above and below the torso
+const WALL_STREAM_RANGE: f32 = 3000.0;
+
+/// Keeps wall chunks around the torso and removes the ones that are too far away,
+/// so the walls go on however far the player falls.
+pub fn stream_walls(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
+    wall_query: Query<(Entity, &Wall)>,
+    player_query: Query<&Transform, With<PlayerTorso>>,
+    world_shift: Res<WorldShift>,
+    shaft: Res<Shaft>,
+    theme_handle: Res<ThemeHandle>,
+    themes: Res<Assets<Theme>>,
+    asset_server: Res<AssetServer>,
+    zones: DepthZones,
 ) {
-    let grey_material = materials.add(Color::srgb(0.15, 0.15, 0.15));
-
-    // walls
-    commands
-        .spawn(Mesh2d(meshes.add(Rectangle::new(20.0, 15000.0))))
-        .insert(Wall)
-        .insert(MeshMaterial2d(grey_material.clone()))
-        .insert(Collider::cuboid(10.0, 7500.0))
-        .insert(Friction {
-            coefficient: 0.0,
-            combine_rule: CoefficientCombineRule::Min,
-        })
-        .insert(Transform::from_xyz(-300.0, 0.0, 0.0))
-        .insert(Name::new("wall"));
-
-    commands
-        .spawn(Mesh2d(meshes.add(Rectangle::new(20.0, 15000.0))))
-        .insert(Wall)
-        .insert(MeshMaterial2d(grey_material.clone()))
-        .insert(Collider::cuboid(10.0, 7500.0))
-        .insert(Friction {
-            coefficient: 0.0,
-            combine_rule: CoefficientCombineRule::Min,
-        })
-        .insert(Transform::from_xyz(300.0, 0.0, 0.0))
-        .insert(Name::new("wall"));
+    let Ok(player_transform) = player_query.single() else {
+        return;
+    };
+
+    // chunk indices count from the start of the run, so they do not change when the world shifts
+    let torso_y = player_transform.translation.y as f64 - world_shift.0;
+    let stream_range = WALL_STREAM_RANGE as f64;
+    let lowest = chunk_at(torso_y - stream_range);
+    let highest = chunk_at(torso_y + stream_range);
+
+    let mut existing = HashSet::new();
+    for (wall_entity, wall) in wall_query.iter() {
+        if (lowest..=highest).contains(&wall.chunk) {
+            existing.insert(wall.chunk);
+        } else {
+            commands.entity(wall_entity).despawn();
+        }
+    }
+
+    let missing: Vec<i64> = (lowest..=highest)
+        .filter(|chunk| !existing.contains(chunk))
+        .collect();
+    if missing.is_empty() {
+        return;
+    }
+
+    let theme = themes.get(&theme_handle.0);
+    let zone_list = zones.get();
+
+    for chunk in missing {
+        let chunk_y = (chunk_center(chunk) + world_shift.0) as f32;
+        // each chunk looks like the zone its middle is in
+        let zone = zone_list.blend_at(depth_at_run_y(chunk_center(chunk)));
+        let wall_material = materials.add(match theme {
+            Some(theme) => theme.walls_material(&asset_server, &zone),
+            None => ColorMaterial::from(Color::srgb(0.15, 0.15, 0.15)),
+        });
+
+        for side in [-1.0, 1.0] {
+            let (mesh, collider) = shaft.wall_chunk(chunk, side);
+            commands
+                .spawn(Mesh2d(meshes.add(mesh)))
+                .insert(Wall { chunk })
+                .insert(MeshMaterial2d(wall_material.clone()))
+                .insert(collider)
+                .insert(Friction {
+                    coefficient: 0.0,
+                    combine_rule: CoefficientCombineRule::Min,
+                })
+                .insert(Transform::from_xyz(0.0, chunk_y, 0.0))
+                .insert(Name::new("wall"));
+        }
+    }
+}
+
+pub fn seed_obstacles(mut obstacles_data: ResMut<ObstaclesData>, run_seed: Res<RunSeed>) {
+    obstacles_data.rng = StdRng::seed_from_u64(run_seed.seed);
+    obstacles_data.run_time = 0.0;
+    obstacles_data.last_spawned = 0.0;
+    obstacles_data.last_pickup = 0.0;
+    obstacles_data.pending.clear();
 }
 
-const MAX_SPAWN_DELTA_S: f32 = 0.5;
-const FRAME_OBSTACLE_SPAWN_CHANCE: f64 = 0.05;
 const OVER_PLAYER_DESPAWN: f32 = 1000.0;
-const MIN_OBSTACLE_DISTANCE: f32 = 700.0;
+const ROD_WIDTH: f32 = 6.0;
 const UNDER_PLAYER_SPAWN: f32 = 1000.0;
-const MIN_SPAWN_HEIGHT: f32 = -4000.0;
+// obstacles are kept clear of the walls this far above and below the point they spawn at
+const SPAWN_CLEARANCE: f32 = 150.0;
+// the player always fits through next to an obstacle when it spawns, however narrow the shaft is
+const PASSABLE_GAP: f32 = 100.0;
+// minimum seconds between two pickups
+const PICKUP_DELAY: f32 = 1.0;
+// pickups do not spawn this close above or below an obstacle
+const PICKUP_SPACING: f32 = 150.0;
 
 pub fn manage_obstacles(
     mut commands: Commands,
@@ -70,36 +170,174 @@ pub fn manage_obstacles(
     player_query: Query<&Transform, With<PlayerTorso>>,
     theme_handle: Res<ThemeHandle>,
     themes: Res<Assets<Theme>>,
+    catalogs: ObstacleCatalogs,
+    segments: Segments,
+    asset_server: Res<AssetServer>,
+    player_data: Res<PlayerData>,
+    shaft: Res<Shaft>,
+    world_shift: Res<WorldShift>,
+    zones: DepthZones,
     time: Res<Time>,
 ) {
     let theme = themes.get(&theme_handle.0);
+    let catalog = catalogs.get();
+    obstacles_data.run_time += time.delta_secs();
+    let difficulty = catalog.difficulty_at(player_data.score);
 
     if let Ok(player_transform) = player_query.single() {
+        let spawn_y = player_transform.translation.y - UNDER_PLAYER_SPAWN;
+        // obstacles follow the rules of the zone they spawn in, not the one the player is in
+        let zone = zones.get().blend_at(depth_at(spawn_y, &world_shift));
+
+        // spawn the pieces of the current segment as they come into range
+        let ObstaclesData { rng, pending, .. } = &mut *obstacles_data;
+        let due = pending
+            .iter()
+            .take_while(|piece| piece.position.y >= spawn_y)
+            .count();
+        // space the pieces spawned so far can reach when they turn
+        let mut blocked: Vec<Rect> = Vec::new();
+        for piece in pending.drain(..due) {
+            // segments are made for the widest shaft and squeezed into narrower parts
+            let x_range = free_x_range(&shaft, &world_shift, piece.position.y);
+            let center = (*x_range.start() + *x_range.end()) as f32 / 2.0;
+            let half_width = (*x_range.end() - *x_range.start()) as f32 / 2.0;
+            let size = fit_size(&piece.kind, piece.size, &x_range);
+            let position = Vec2::new(
+                fit_x(
+                    center + piece.position.x * half_width / SHAFT_HALF_WIDTH,
+                    turned_width(&piece.kind, size),
+                    &x_range,
+                ),
+                piece.position.y,
+            );
+
+            if !matches!(piece.kind.hazard, Some(Hazard::SlowZone { .. })) {
+                let bounds =
+                    Rect::from_center_size(position, Vec2::splat(turned_width(&piece.kind, size)));
+                if !leaves_gap(&blocked, bounds, &x_range) {
+                    debug!(
+                        "Skipping segment piece '{}' that would seal the shaft.",
+                        piece.kind.name
+                    );
+                    continue;
+                }
+                blocked.push(bounds);
+            }
+
+            spawn_obstacle(
+                &mut commands,
+                &mut meshes,
+                &mut materials,
+                &asset_server,
+                x_range,
+                &piece.kind,
+                size,
+                position,
+                piece.angle,
+                rng,
+                theme,
+            );
+        }
+        if due > 0 {
+            obstacles_data.last_spawned = obstacles_data.run_time;
+        }
+
         // create new obstacle if conditions are met
-        if time.elapsed_secs() - obstacles_data.last_spawned > MAX_SPAWN_DELTA_S
-            && obstacles_data.rng.gen_bool(FRAME_OBSTACLE_SPAWN_CHANCE)
+        if obstacles_data.pending.is_empty()
+            && obstacles_data.run_time - obstacles_data.last_spawned > difficulty.spawn_delay
+            && obstacles_data
+                .rng
+                .gen_bool((difficulty.spawn_chance * zone.spawn_rate()).clamp(0.0, 1.0))
         {
-            let new_y = player_transform.translation.y - UNDER_PLAYER_SPAWN;
+            let new_y = spawn_y;
 
-            let min_dist_sq = MIN_OBSTACLE_DISTANCE * MIN_OBSTACLE_DISTANCE;
+            let min_dist_sq = difficulty.min_distance * difficulty.min_distance;
             let too_close = obstacles.iter().any(|(_, t)| {
                 let dy = t.translation.y - new_y;
                 (dy * dy) < min_dist_sq
             });
 
-            if !too_close && player_transform.translation.y >= MIN_SPAWN_HEIGHT {
-                spawn_random_obstacle(
+            if !too_close {
+                // single obstacles fill in while no segment is unlocked
+                let segment = if obstacles_data
+                    .rng
+                    .gen_bool(difficulty.segment_chance.clamp(0.0, 1.0))
+                {
+                    segments.pick(catalog, &mut obstacles_data.rng, player_data.score)
+                } else {
+                    None
+                };
+
+                if let Some(segment) = segment {
+                    // the pieces spawn as the player gets close
+                    obstacles_data.pending = segment.place(
+                        catalog,
+                        &mut obstacles_data.rng,
+                        difficulty.size_scale,
+                        new_y,
+                    );
+                    info!("Spawning segment '{}'.", segment.name);
+                } else if let Some(kind) =
+                    catalog.pick(&mut obstacles_data.rng, player_data.score, |kind| {
+                        zone.weight(&kind.name)
+                    })
+                {
+                    spawn_random_obstacle(
+                        &mut commands,
+                        &mut meshes,
+                        &mut materials,
+                        &asset_server,
+                        free_x_range(&shaft, &world_shift, new_y),
+                        kind,
+                        difficulty.size_scale,
+                        &mut obstacles_data.rng,
+                        new_y,
+                        theme,
+                    );
+
+                    obstacles_data.last_spawned = obstacles_data.run_time;
+                }
+            }
+        }
+
+        // pickups fill the gaps between obstacles
+        if obstacles_data.run_time - obstacles_data.last_pickup > PICKUP_DELAY
+            && obstacles_data
+                .rng
+                .gen_bool(difficulty.pickup_chance.clamp(0.0, 1.0))
+        {
+            let new_y = spawn_y;
+            let blocked = obstacles
+                .iter()
+                .any(|(_, t)| (t.translation.y - new_y).abs() < PICKUP_SPACING)
+                || obstacles_data
+                    .pending
+                    .iter()
+                    .any(|piece| (piece.position.y - new_y).abs() < PICKUP_SPACING);
+
+            if !blocked
+                && let Some(kind) = catalog.pick_pickup(&mut obstacles_data.rng, player_data.score)
+            {
+                let x_range = free_x_range(&shaft, &world_shift, new_y);
+                let x_low = *x_range.start() as f32 + PICKUP_RADIUS * 2.0;
+                let x_high = *x_range.end() as f32 - PICKUP_RADIUS * 2.0;
+                let new_x = if x_low < x_high {
+                    obstacles_data.rng.gen_range(x_low..x_high)
+                } else {
+                    (x_low + x_high) / 2.0
+                };
+
+                spawn_pickup(
                     &mut commands,
                     &mut meshes,
                     &mut materials,
-                    -295..=295,
-                    120..=180,
-                    &mut obstacles_data.rng,
-                    new_y,
+                    &asset_server,
+                    kind,
+                    Vec2::new(new_x, new_y),
                     theme,
                 );
-
-                obstacles_data.last_spawned = time.elapsed_secs();
+                obstacles_data.last_pickup = obstacles_data.run_time;
             }
         }
 
@@ -116,110 +354,233 @@ pub fn manage_obstacles(
     }
 }
 
+/// Free space between the walls around the world y, rounded inwards.
+fn free_x_range(shaft: &Shaft, world_shift: &WorldShift, y: f32) -> RangeInclusive<i32> {
+    let y = y as f64 - world_shift.0;
+    let clearance = SPAWN_CLEARANCE as f64;
+    let (left, right) = shaft.free_range(y - clearance, y + clearance);
+    (left.ceil() as i32)..=(right.floor() as i32)
+}
+
+/// Widest the obstacle gets from side to side at any angle it can turn to.
+fn turned_width(kind: &ObstacleKind, size: Vec2) -> f32 {
+    match kind.shape {
+        ObstacleShape::Circle => size.x,
+        _ => size.length(),
+    }
+}
+
+/// Shrinks the obstacle so it leaves at least `PASSABLE_GAP` of the free space open.
+/// Slow zones do not block the player and keep their size.
+fn fit_size(kind: &ObstacleKind, size: Vec2, x_range: &RangeInclusive<i32>) -> Vec2 {
+    if matches!(kind.hazard, Some(Hazard::SlowZone { .. })) {
+        return size;
+    }
+    let max_width = ((*x_range.end() - *x_range.start()) as f32 - PASSABLE_GAP).max(0.0);
+    let width = turned_width(kind, size);
+    if width > max_width {
+        size * (max_width / width)
+    } else {
+        size
+    }
+}
+
+/// Keeps an obstacle of the width between the walls.
+/// Without room for a passable gap on both sides it moves against the closer wall,
+/// so the whole gap is on the other side.
+fn fit_x(x: f32, width: f32, x_range: &RangeInclusive<i32>) -> f32 {
+    let x_low = *x_range.start() as f32 + width / 2.0;
+    let x_high = *x_range.end() as f32 - width / 2.0;
+    if x_low >= x_high {
+        (x_low + x_high) / 2.0
+    } else if x_high - x_low >= 2.0 * PASSABLE_GAP {
+        x.clamp(x_low, x_high)
+    } else if x < (x_low + x_high) / 2.0 {
+        x_low
+    } else {
+        x_high
+    }
+}
+
+/// True if the player still fits past `bounds` and the other blocked space next to it.
+fn leaves_gap(blocked: &[Rect], bounds: Rect, x_range: &RangeInclusive<i32>) -> bool {
+    let mut spans: Vec<(f32, f32)> = blocked
+        .iter()
+        .filter(|other| other.min.y < bounds.max.y && other.max.y > bounds.min.y)
+        .chain([&bounds])
+        .map(|other| (other.min.x, other.max.x))
+        .collect();
+    spans.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut x = *x_range.start() as f32;
+    for (left, right) in spans {
+        if left - x >= PASSABLE_GAP {
+            return true;
+        }
+        x = x.max(right);
+    }
+    *x_range.end() as f32 - x >= PASSABLE_GAP
+}
+
 fn spawn_random_obstacle(
     commands: &mut Commands,
     meshes: &mut ResMut<Assets<Mesh>>,
     materials: &mut ResMut<Assets<ColorMaterial>>,
+    asset_server: &AssetServer,
     x_range: RangeInclusive<i32>,
-    size_range: RangeInclusive<i32>,
+    kind: &ObstacleKind,
+    size_scale: (f32, f32),
     gen_rng: &mut StdRng,
     y_height: f32,
     theme_opt: Option<&Theme>,
 ) {
-    let obj_width = gen_rng.gen_range(size_range.clone()) as f32;
-    let obj_height = gen_rng.gen_range(size_range.clone()) as f32;
-    let x_low = *x_range.start() as f32 + (obj_width / 2.0);
-    let x_high = *x_range.end() as f32 - (obj_width / 2.0);
-    let new_x = gen_rng.gen_range(x_low..x_high);
+    let size = fit_size(kind, kind.pick_size(gen_rng, size_scale), &x_range);
+    let width = turned_width(kind, size);
+    let x_low = *x_range.start() as f32 + (width / 2.0);
+    let x_high = *x_range.end() as f32 - (width / 2.0);
+    let new_x = if x_low < x_high {
+        fit_x(gen_rng.gen_range(x_low..x_high), width, &x_range)
+    } else {
+        (x_low + x_high) / 2.0
+    };
     let new_y = y_height;
 
-    let mut grayscale = true;
-    let mut color_variation = 0.1;
-    let mut base_color = Vec3::new(0.3, 0.3, 0.3); // x = r, y = g, z = b
+    spawn_obstacle(
+        commands,
+        meshes,
+        materials,
+        asset_server,
+        x_range,
+        kind,
+        size,
+        Vec2::new(new_x, new_y),
+        None,
+        gen_rng,
+        theme_opt,
+    );
+}
 
-    // TODO: custom image textures
-    if let Some(theme) = theme_opt {
-        grayscale = theme.obstacles_grayscale;
-        color_variation = theme.obstacles_color_variation as f32;
-        base_color = theme.obstacles_base_color.to_vec();
-    }
+/// Spawns an obstacle of the kind at the position.
+/// The angle is rolled the way the kind wants if it is `None`.
+fn spawn_obstacle(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    asset_server: &AssetServer,
+    x_range: RangeInclusive<i32>,
+    kind: &ObstacleKind,
+    size: Vec2,
+    mut position: Vec2,
+    angle: Option<f32>,
+    gen_rng: &mut StdRng,
+    theme_opt: Option<&Theme>,
+) {
+    let look = ObstacleLook::roll(kind.shape, kind.hazard, gen_rng);
 
-    let c_red = gen_rng.gen_range(
-        (base_color.x - color_variation).clamp(0.0, 1.0)
-            ..(base_color.x + color_variation).clamp(0.0, 1.0),
-    );
-    let c_green = gen_rng.gen_range(
-        (base_color.y - color_variation).clamp(0.0, 1.0)
-            ..(base_color.y + color_variation).clamp(0.0, 1.0),
-    );
-    let c_blue = gen_rng.gen_range(
-        (base_color.z - color_variation).clamp(0.0, 1.0)
-            ..(base_color.z + color_variation).clamp(0.0, 1.0),
-    );
-    let obj_color = if grayscale {
-        Color::srgb(c_red, c_red, c_red)
+    let angle = match angle {
+        Some(angle) => angle,
+        None if kind.random_rotation => gen_rng.gen_range(0.0..360.0),
+        None => 0.0,
+    };
+    let spin = kind.pick_spin(gen_rng);
+
+    let mut rigid_body = kind.body.rigid_body();
+    let moving = kind.motion.map(|motion| {
+        let path = motion.resolve(
+            gen_rng,
+            size,
+            position,
+            angle,
+            (*x_range.start() as f32, *x_range.end() as f32),
+        );
+        MovingObstacle { path, age: 0.0 }
+    });
+    let rotation = match moving {
+        Some(moving) => {
+            // the transform is set every tick, rapier derives the velocity for contacts from it
+            rigid_body = RigidBody::KinematicPositionBased;
+            let (start_position, start_angle) = moving.path.transform_at(0.0);
+            position = start_position;
+            Quat::from_rotation_z(start_angle)
+        }
+        None => Quat::from_rotation_z(angle),
+    };
+
+    let mesh = if kind.hazard == Some(Hazard::Spikes) && kind.shape == ObstacleShape::Rectangle {
+        spikes_mesh(size)
     } else {
-        Color::srgb(c_red, c_green, c_blue)
+        kind.shape.mesh(size)
     };
 
-    let rotation = Quat::from_rotation_z(gen_rng.gen_range(0.0..360.0));
+    let mut obstacle = commands.spawn(Mesh2d(meshes.add(mesh)));
+    obstacle
+        .insert(MeshMaterial2d(
+            materials.add(look.material(theme_opt, asset_server)),
+        ))
+        .insert(ObstacleObject)
+        .insert(look)
+        .insert(kind.shape.collider(size))
+        .insert(Transform {
+            translation: position.extend(0.0),
+            rotation,
+            ..default()
+        })
+        .insert(Name::new(format!("obstacle_{}", kind.name)))
+        .insert(rigid_body)
+        .insert(GravityScale(kind.gravity_scale))
+        .insert(Friction::coefficient(kind.friction))
+        .insert(Restitution::coefficient(kind.restitution))
+        .insert(ColliderMassProperties::Density(kind.density))
+        .insert(Velocity::angular(spin));
 
-    match gen_rng.gen_range(0..3) {
-        0 => {
-            commands
-                .spawn(Mesh2d(meshes.add(Rectangle::new(obj_width, obj_height))))
-                .insert(MeshMaterial2d(materials.add(obj_color)))
-                .insert(ObstacleObject)
-                .insert(Collider::cuboid(obj_width / 2.0, obj_height / 2.0))
-                .insert(Transform {
-                    translation: Vec3 {
-                        x: new_x,
-                        y: new_y,
-                        z: 0.0,
-                    },
-                    rotation,
-                    ..default()
-                })
-                .insert(Name::new("obstacle_rectangular"))
-                .insert(RigidBody::Dynamic)
-                .insert(GravityScale(0.1));
-        }
-        1 => {
-            commands
-                .spawn(Mesh2d(meshes.add(Circle::new(obj_width / 2.0))))
-                .insert(MeshMaterial2d(materials.add(obj_color)))
-                .insert(ObstacleObject)
-                .insert(Collider::ball(obj_width / 2.0))
-                .insert(Transform::from_xyz(new_x, new_y, 0.0))
-                .insert(Name::new("obstacle_round"))
-                .insert(RigidBody::Dynamic)
-                .insert(GravityScale(0.1));
+    if let Some(hazard) = kind.hazard {
+        obstacle
+            .insert(hazard)
+            .insert(ActiveEvents::COLLISION_EVENTS);
+
+        match hazard {
+            Hazard::Bouncy { restitution } => {
+                obstacle.insert(Restitution {
+                    coefficient: restitution,
+                    combine_rule: CoefficientCombineRule::Max,
+                });
+            }
+            Hazard::SlowZone { .. } => {
+                obstacle.insert(Sensor);
+            }
+            Hazard::Spikes | Hazard::Sticky { .. } => {}
         }
-        2 => {
-            let point_a = Vec2::new(0.0, obj_height / 2.0);
-            let point_b = Vec2::new(-(obj_width / 2.0), -(obj_height / 2.0));
-            let point_c = Vec2::new(obj_width / 2.0, -(obj_height / 2.0));
+    }
 
-            commands
-                .spawn(Mesh2d(
-                    meshes.add(Triangle2d::new(point_a, point_b, point_c)),
-                ))
-                .insert(MeshMaterial2d(materials.add(obj_color)))
-                .insert(ObstacleObject)
-                .insert(Collider::triangle(point_a, point_b, point_c))
-                .insert(Transform {
-                    translation: Vec3 {
-                        x: new_x,
-                        y: new_y,
-                        z: 0.0,
-                    },
-                    rotation,
-                    ..default()
-                })
-                .insert(Name::new("obstacle_triangular"))
-                .insert(RigidBody::Dynamic)
-                .insert(GravityScale(0.1));
+    if let Some(moving) = moving {
+        obstacle.insert(moving);
+
+        if let Some(length) = moving.path.rod_length() {
+            let mut rod_color = Color::srgb(0.15, 0.15, 0.15);
+            if let Some(theme) = theme_opt {
+                rod_color = theme.walls_color.to_color();
+            }
+
+            // points straight up to the pivot, the obstacle rotates with the swing
+            obstacle.with_child((
+                Mesh2d(meshes.add(Rectangle::new(ROD_WIDTH, length))),
+                MeshMaterial2d(materials.add(rod_color)),
+                Transform::from_xyz(0.0, length / 2.0, -0.1),
+                Name::new("rod"),
+            ));
         }
-        _ => unreachable!(),
+    }
+}
+
+pub fn move_obstacles(
+    mut obstacles: Query<(&mut MovingObstacle, &mut Transform)>,
+    time: Res<Time>,
+) {
+    for (mut moving, mut transform) in obstacles.iter_mut() {
+        moving.age += time.delta_secs();
+        let (position, angle) = moving.path.transform_at(moving.age);
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+        transform.rotation = Quat::from_rotation_z(angle);
     }
 }
@@ -0,0 +1,91 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game_states::{GameState, NewRun},
+    player::PlayerData,
+};
+
+pub const TIME_ATTACK_LIMIT_S: f32 = 90.0;
+const SECONDS_PER_DAY: u64 = 60 * 60 * 24;
+
+/// Rules of the current run, picked in the main menu.
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GameMode {
+    // new seed for every run
+    #[default]
+    Classic,
+    // the same seed for everyone, changes once a day
+    Daily,
+    // get as far as possible before the time runs out
+    TimeAttack,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 3] = [GameMode::Classic, GameMode::Daily, GameMode::TimeAttack];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::Classic => "Classic",
+            GameMode::Daily => "Daily",
+            GameMode::TimeAttack => "Time Attack",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            GameMode::Classic => "A new seed every run",
+            GameMode::Daily => "One seed per day, the same for everyone",
+            GameMode::TimeAttack => "Fall as far as you can in 90 seconds",
+        }
+    }
+}
+
+/// Seed of the daily mode, derived from the current UTC date.
+pub fn daily_seed() -> u64 {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs() / SECONDS_PER_DAY);
+    // spreads consecutive days over the whole seed range
+    days.wrapping_mul(0x9E37_79B9_7F4A_7C15)
+}
+
+// elapsed time when the current run started
+#[derive(Resource, Default)]
+pub struct RunStart(pub f32);
+
+pub struct GameModePlugin {
+    pub mode: GameMode,
+}
+
+impl Plugin for GameModePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.mode)
+            .init_resource::<RunStart>()
+            .add_systems(NewRun, start_run_timer)
+            .add_systems(
+                Update,
+                check_time_attack.run_if(in_state(GameState::InGame)),
+            );
+    }
+}
+
+pub fn start_run_timer(mut run_start: ResMut<RunStart>, time: Res<Time>) {
+    run_start.0 = time.elapsed_secs();
+}
+
+pub fn check_time_attack(
+    mode: Res<GameMode>,
+    run_start: Res<RunStart>,
+    time: Res<Time>,
+    mut player_data: ResMut<PlayerData>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if *mode == GameMode::TimeAttack && time.elapsed_secs() - run_start.0 >= TIME_ATTACK_LIMIT_S {
+        next_state.set(GameState::GameOver);
+        player_data.last_death_str = "Time is up.".to_string();
+        info!("Time attack run is over.");
+    }
+}
@@ -1,27 +1,77 @@
-use crate::environment::ObstacleObject;
-use crate::player::PlayerData;
-use crate::player_setup::PlayerBodyPart;
+use crate::actions::{Action, ActionInput, key_name};
+use crate::environment::{ObstacleObject, Wall};
+use crate::player::{PlayerData, WorldShift};
+use crate::player_setup::{PLAYER_START_Y, PlayerBodyPart};
+use crate::seed::RunSeed;
+use crate::settings::Settings;
+use crate::skeleton::skeleton_loaded;
 use crate::themes::{Theme, ThemeHandle};
+use bevy::ecs::schedule::ScheduleLabel;
 use bevy::prelude::*;
 
 #[derive(Component)]
 pub struct GameOverText;
 
-#[derive(Component)]
-pub struct PreGameText;
-
 #[derive(States, Debug, Clone, PartialEq, Eq, Hash)]
 pub enum GameState {
     PreGame,
     InGame,
+    Paused,
     GameOver,
 }
 
+/// Clears the previous run and sets up a new one.
+/// Runs when entering the game from the start or game over screen
+/// and when restarting from the pause menu, but not when resuming a paused run.
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NewRun;
+
+pub struct GameStatePlugin {
+    // skips the start screen
+    pub start_in_game: bool,
+}
+
+impl Plugin for GameStatePlugin {
+    fn build(&self, app: &mut App) {
+        // waits for the skeleton, so headless runs and replays do not start with the built-in one
+        if self.start_in_game {
+            app.add_systems(Update, start_game.run_if(skeleton_loaded.and(run_once)));
+        }
+
+        app.init_schedule(NewRun)
+            .add_systems(NewRun, clear_run)
+            .insert_state(GameState::PreGame)
+            .add_systems(
+                OnTransition {
+                    exited: GameState::PreGame,
+                    entered: GameState::InGame,
+                },
+                start_new_run,
+            )
+            .add_systems(OnEnter(GameState::GameOver), spawn_game_over_ui)
+            .add_systems(OnExit(GameState::GameOver), despawn_game_over_ui)
+            .add_systems(
+                OnTransition {
+                    exited: GameState::GameOver,
+                    entered: GameState::InGame,
+                },
+                start_new_run,
+            )
+            // runs only start with the skeleton that is recorded in the replay
+            .add_systems(
+                Update,
+                handle_game_over_input.run_if(in_state(GameState::GameOver).and(skeleton_loaded)),
+            );
+    }
+}
+
 // TODO: better UIs
 
 pub fn spawn_game_over_ui(
     mut commands: Commands,
-    mut player_data: ResMut<PlayerData>,
+    player_data: Res<PlayerData>,
+    run_seed: Res<RunSeed>,
+    settings: Res<Settings>,
     theme_handle: Res<ThemeHandle>,
     themes: Res<Assets<Theme>>,
 ) {
@@ -31,10 +81,16 @@ pub fn spawn_game_over_ui(
         text_color = theme.text_color.to_color();
     }
 
+    let key = |action| key_name(settings.key_bindings.key(action));
     commands.spawn((
         Text::new(format!(
-            "{}\nPress Space to restart\nScore: {}",
-            player_data.last_death_str, player_data.score
+            "{}\nPress {} or tap to restart\nPress {} to retry this seed\nPress {} for the main menu\nScore: {}\nSeed: {}",
+            player_data.last_death_str,
+            key(Action::Confirm),
+            key(Action::Retry),
+            key(Action::Dismiss),
+            player_data.score,
+            run_seed.seed
         )),
         TextFont {
             font_size: 30.0,
@@ -53,90 +109,63 @@ pub fn spawn_game_over_ui(
         },
         GameOverText,
     ));
-
-    player_data.broken_parts.clear();
 }
 
-pub fn despawn_game_over_ui(
-    mut commands: Commands,
-    query: Query<Entity, With<GameOverText>>,
-    player_part_query: Query<Entity, With<PlayerBodyPart>>,
-    mut player_data: ResMut<PlayerData>,
-    obstacle_query: Query<Entity, With<ObstacleObject>>,
-) {
+pub fn despawn_game_over_ui(mut commands: Commands, query: Query<Entity, With<GameOverText>>) {
     for entity in query.iter() {
         commands.entity(entity).despawn();
     }
-
-    for player_part in player_part_query.iter() {
-        commands.entity(player_part).despawn();
-    }
-
-    let mut despawn_count = 0;
-    for obstacle in obstacle_query.iter() {
-        commands.entity(obstacle).despawn();
-        despawn_count += 1;
-    }
-    info!("Despawned {} obstacles.", despawn_count);
-
-    player_data.score = 0;
-    player_data.last_y_position = 200.0;
 }
 
 pub fn handle_game_over_input(
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    action_input: Res<ActionInput>,
     mut next_state: ResMut<NextState<GameState>>,
+    mut run_seed: ResMut<RunSeed>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::Space) {
+    if action_input.just_pressed(Action::Confirm) || action_input.tapped() {
         next_state.set(GameState::InGame);
+    } else if action_input.just_pressed(Action::Retry) {
+        run_seed.retry = true;
+        next_state.set(GameState::InGame);
+    } else if action_input.just_pressed(Action::Dismiss) {
+        next_state.set(GameState::PreGame);
     }
 }
 
-pub fn spawn_pre_game_ui(mut commands: Commands) {
-    commands.spawn((
-        Text::new("Press Space to start."),
-        TextFont {
-            font_size: 30.0,
-            ..Default::default()
-        },
-        TextColor(Color::srgb(1.0, 1.0, 1.0)),
-        TextLayout::new_with_justify(JustifyText::Center),
-        Node {
-            position_type: PositionType::Absolute,
-            width: Val::Percent(100.0),
-            height: Val::Percent(100.0),
-            top: Val::Percent(45.0),
-            justify_content: JustifyContent::Center,
-            align_items: AlignItems::Center,
-            ..default()
-        },
-        PreGameText,
-    ));
+pub fn start_new_run(world: &mut World) {
+    world.run_schedule(NewRun);
 }
 
-pub fn despawn_pre_game_ui(
+pub fn clear_run(
     mut commands: Commands,
-    query: Query<Entity, With<PreGameText>>,
     player_part_query: Query<Entity, With<PlayerBodyPart>>,
+    obstacle_query: Query<Entity, With<ObstacleObject>>,
+    wall_query: Query<Entity, With<Wall>>,
     mut player_data: ResMut<PlayerData>,
+    mut world_shift: ResMut<WorldShift>,
 ) {
-    for entity in query.iter() {
-        commands.entity(entity).despawn();
-    }
-
     for player_part in player_part_query.iter() {
         commands.entity(player_part).despawn();
     }
 
+    let mut despawn_count = 0;
+    for obstacle in obstacle_query.iter() {
+        commands.entity(obstacle).despawn();
+        despawn_count += 1;
+    }
+    info!("Despawned {} obstacles.", despawn_count);
+
+    // the chunks are placed again around the new player
+    for wall in wall_query.iter() {
+        commands.entity(wall).despawn();
+    }
+
+    player_data.broken_parts.clear();
     player_data.score = 0;
-    player_data.last_y_position = 200.0;
+    player_data.last_y_position = PLAYER_START_Y;
+    world_shift.0 = 0.0;
 }
 
-pub fn handle_pre_game_input(
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    mut next_state: ResMut<NextState<GameState>>,
-) {
-    if keyboard_input.just_pressed(KeyCode::Space) {
-        next_state.set(GameState::InGame);
-    }
+pub fn start_game(mut next_state: ResMut<NextState<GameState>>) {
+    next_state.set(GameState::InGame);
 }
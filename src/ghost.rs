@@ -0,0 +1,302 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game_states::{GameState, NewRun},
+    player::{PlayerData, WorldShift},
+    player_setup::{PlayerBodyPart, PlayerTorso},
+    replay::{Replay, ReplayRecorder, save_replay},
+    seed::{RunSeed, roll_run_seed},
+    themes::{Theme, ThemeHandle},
+    zones::depth_at,
+};
+
+const GHOST_ALPHA: f32 = 0.3;
+
+/// Personal best run for a seed, with the pose of every body part per tick
+/// so it can be drawn next to the live player.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct GhostRun {
+    pub replay: Replay,
+    // body part names, in the same order as the poses of each frame
+    pub parts: Vec<String>,
+    pub frames: Vec<GhostFrame>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct GhostFrame {
+    pub score: i32,
+    // meters below the start of the run the torso is at
+    #[serde(default)]
+    pub depth: f32,
+    // x, y and rotation of each body part. y does not include the world shift.
+    pub poses: Vec<(f32, f32, f32)>,
+}
+
+impl GhostRun {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        Ok(ron::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, ron::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+pub fn ghost_path(seed: u64) -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| {
+        dir.join("falling")
+            .join("ghosts")
+            .join(format!("{}.ghost.ron", seed))
+    })
+}
+
+#[derive(Resource, Default)]
+pub struct GhostRecorder(pub GhostRun);
+
+// personal best of the current seed, if there is one
+#[derive(Resource, Default)]
+pub struct ActiveGhost {
+    pub run: Option<GhostRun>,
+    pub tick: usize,
+}
+
+#[derive(Component)]
+pub struct GhostPart(pub usize);
+
+#[derive(Component)]
+pub struct GhostText;
+
+pub struct GhostPlugin;
+
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GhostRecorder>()
+            .init_resource::<ActiveGhost>()
+            .add_systems(PostStartup, spawn_ghost_text)
+            .add_systems(NewRun, (load_ghost.after(roll_run_seed), despawn_ghost))
+            .add_systems(OnEnter(GameState::InGame), spawn_ghost)
+            .add_systems(
+                FixedUpdate,
+                (record_ghost_frame, advance_ghost).run_if(in_state(GameState::InGame)),
+            )
+            .add_systems(PostUpdate, update_ghost_text)
+            .add_systems(
+                OnEnter(GameState::GameOver),
+                (save_personal_best.after(save_replay), despawn_ghost),
+            );
+    }
+}
+
+pub fn load_ghost(
+    mut active_ghost: ResMut<ActiveGhost>,
+    mut recorder: ResMut<GhostRecorder>,
+    run_seed: Res<RunSeed>,
+) {
+    recorder.0 = GhostRun::default();
+    active_ghost.tick = 0;
+    active_ghost.run = ghost_path(run_seed.seed)
+        .filter(|path| path.exists())
+        .and_then(|path| match GhostRun::load(&path) {
+            Ok(run) => Some(run),
+            Err(err) => {
+                warn!("Failed to load ghost from {}: {}", path.display(), err);
+                None
+            }
+        });
+
+    if let Some(run) = &active_ghost.run {
+        info!("Racing against personal best of {}.", run.replay.score);
+    }
+}
+
+pub fn spawn_ghost(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    active_ghost: Res<ActiveGhost>,
+    ghost_query: Query<(), With<GhostPart>>,
+    part_query: Query<(&Name, &Mesh2d), With<PlayerBodyPart>>,
+    theme_handle: Res<ThemeHandle>,
+    themes: Res<Assets<Theme>>,
+) {
+    let Some(run) = &active_ghost.run else {
+        return;
+    };
+    if !ghost_query.is_empty() {
+        return;
+    }
+
+    let mut color = Color::srgb(1.0, 1.0, 1.0);
+    if let Some(theme) = themes.get(&theme_handle.0) {
+        color = theme.player_body_color.to_color();
+    }
+    let material = materials.add(color.with_alpha(GHOST_ALPHA));
+
+    // reuses the meshes of the live player, the ghost has no colliders
+    for (name, mesh) in part_query.iter() {
+        if let Some(index) = run.parts.iter().position(|part| part == name.as_str()) {
+            commands.spawn((
+                mesh.clone(),
+                MeshMaterial2d(material.clone()),
+                Transform::from_xyz(0.0, 0.0, -1.0),
+                Visibility::Hidden,
+                GhostPart(index),
+                Name::new(format!("ghost_{}", name.as_str())),
+            ));
+        }
+    }
+}
+
+pub fn despawn_ghost(mut commands: Commands, ghost_query: Query<Entity, With<GhostPart>>) {
+    for ghost_part in ghost_query.iter() {
+        commands.entity(ghost_part).despawn();
+    }
+}
+
+pub fn record_ghost_frame(
+    mut recorder: ResMut<GhostRecorder>,
+    part_query: Query<(&Name, &Transform), With<PlayerBodyPart>>,
+    torso_query: Query<&Transform, With<PlayerTorso>>,
+    player_data: Res<PlayerData>,
+    world_shift: Res<WorldShift>,
+) {
+    let mut parts: Vec<(&Name, &Transform)> = part_query.iter().collect();
+    parts.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
+
+    if recorder.0.parts.is_empty() {
+        recorder.0.parts = parts.iter().map(|(name, _)| name.to_string()).collect();
+    }
+
+    let poses = parts
+        .iter()
+        .map(|(_, transform)| {
+            (
+                transform.translation.x,
+                (transform.translation.y as f64 - world_shift.0) as f32,
+                transform.rotation.to_euler(EulerRot::XYZ).2,
+            )
+        })
+        .collect();
+
+    let depth = torso_query
+        .single()
+        .map_or(0.0, |torso| depth_at(torso.translation.y, &world_shift));
+
+    recorder.0.frames.push(GhostFrame {
+        score: player_data.score,
+        depth,
+        poses,
+    });
+}
+
+pub fn advance_ghost(
+    mut active_ghost: ResMut<ActiveGhost>,
+    mut ghost_query: Query<(&GhostPart, &mut Transform, &mut Visibility)>,
+    world_shift: Res<WorldShift>,
+) {
+    let tick = active_ghost.tick;
+    let Some(run) = &active_ghost.run else {
+        return;
+    };
+
+    // the ghost stays where its run ended
+    let Some(frame) = run.frames.get(tick).or(run.frames.last()) else {
+        return;
+    };
+
+    for (ghost_part, mut transform, mut visibility) in ghost_query.iter_mut() {
+        if let Some((x, y, rotation)) = frame.poses.get(ghost_part.0) {
+            transform.translation.x = *x;
+            transform.translation.y = (*y as f64 + world_shift.0) as f32;
+            transform.rotation = Quat::from_rotation_z(*rotation);
+            *visibility = Visibility::Inherited;
+        }
+    }
+
+    active_ghost.tick += 1;
+}
+
+pub fn save_personal_best(
+    mut recorder: ResMut<GhostRecorder>,
+    replay_recorder: Res<ReplayRecorder>,
+    active_ghost: Res<ActiveGhost>,
+    player_data: Res<PlayerData>,
+) {
+    if let Some(best) = &active_ghost.run
+        && best.replay.score >= player_data.score
+    {
+        return;
+    }
+
+    let Some(path) = ghost_path(replay_recorder.replay().seed) else {
+        warn!("No data directory found, personal best is not saved.");
+        return;
+    };
+
+    recorder.0.replay = replay_recorder.replay().clone();
+    match recorder.0.save(&path) {
+        Ok(()) => info!("New personal best of {}!", player_data.score),
+        Err(err) => warn!(
+            "Failed to save personal best to {}: {}",
+            path.display(),
+            err
+        ),
+    }
+}
+
+pub fn spawn_ghost_text(mut commands: Commands) {
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font_size: 18.0,
+            ..default()
+        },
+        TextColor(Color::srgb(1.0, 1.0, 1.0)),
+        TextLayout::new_with_justify(JustifyText::Center),
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            top: Val::Px(40.0),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        GhostText,
+    ));
+}
+
+pub fn update_ghost_text(
+    mut text_query: Query<&mut Text, With<GhostText>>,
+    active_ghost: Res<ActiveGhost>,
+    torso_query: Query<&Transform, With<PlayerTorso>>,
+    world_shift: Res<WorldShift>,
+    game_state: Res<State<GameState>>,
+) {
+    let Ok(mut text) = text_query.single_mut() else {
+        return;
+    };
+
+    let ghost_depth = active_ghost.run.as_ref().and_then(|run| {
+        run.frames
+            .get(active_ghost.tick.saturating_sub(1))
+            .or(run.frames.last())
+            .map(|frame| frame.depth)
+    });
+
+    text.0 = match (ghost_depth, torso_query.single()) {
+        (Some(ghost_depth), Ok(torso))
+            if matches!(game_state.get(), GameState::InGame | GameState::Paused) =>
+        {
+            let depth = depth_at(torso.translation.y, &world_shift);
+            format!("Ghost: {:+.1} m", depth - ghost_depth)
+        }
+        _ => "".to_string(),
+    };
+}
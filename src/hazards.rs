@@ -0,0 +1,281 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+use bevy::render::mesh::PrimitiveTopology;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy_kira_audio::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game_states::{GameState, NewRun},
+    pickups::PowerUps,
+    player::{PlayerData, handle_collision},
+    player_setup::{PLAYER_ANGULAR_DAMPING, PLAYER_LINEAR_DAMPING, PlayerBodyPart},
+    themes::{Theme, ThemeHandle},
+};
+
+// spikes are drawn about this wide
+const SPIKE_WIDTH: f32 = 30.0;
+
+/// Special effect of an obstacle on contact with the player.
+#[derive(Component, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Reflect)]
+pub enum Hazard {
+    // breaks the touched body part at once, no matter how soft the touch
+    Spikes,
+    // bounces the player off without damage
+    Bouncy { restitution: f32 },
+    // holds the touched body part in place for duration seconds
+    Sticky { duration: f32 },
+    // sensor that raises the damping of every body part inside it
+    SlowZone { damping: f32 },
+}
+
+impl Hazard {
+    /// Contacts with these do not cause the usual damage.
+    pub fn replaces_damage(&self) -> bool {
+        matches!(self, Hazard::Spikes | Hazard::Bouncy { .. })
+    }
+}
+
+// body part held in place by a sticky surface
+#[derive(Component)]
+pub struct Stuck {
+    // child of the part with the fixed joint to the surface
+    pub joint: Entity,
+    // seconds until the part is released
+    pub remaining: f32,
+}
+
+// slow zones each body part is inside
+#[derive(Resource, Default)]
+pub struct SlowZoneOverlaps(pub HashMap<Entity, HashSet<Entity>>);
+
+pub struct HazardPlugin;
+
+impl Plugin for HazardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SlowZoneOverlaps>()
+            .add_systems(NewRun, clear_slow_zones)
+            .add_systems(
+                FixedUpdate,
+                handle_hazard_collisions
+                    .after(PhysicsSet::Writeback)
+                    .after(handle_collision)
+                    .run_if(in_state(GameState::InGame)),
+            )
+            .add_systems(
+                FixedUpdate,
+                (release_stuck_parts, apply_slow_zones).before(PhysicsSet::SyncBackend),
+            );
+    }
+}
+
+pub fn clear_slow_zones(mut overlaps: ResMut<SlowZoneOverlaps>) {
+    overlaps.0.clear();
+}
+
+pub fn handle_hazard_collisions(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    hazard_query: Query<(&Hazard, &Transform)>,
+    part_query: Query<(&Name, &PlayerBodyPart, &Transform, Has<Stuck>)>,
+    color_query: Query<&MeshMaterial2d<ColorMaterial>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut overlaps: ResMut<SlowZoneOverlaps>,
+    mut player_data: ResMut<PlayerData>,
+    mut next_state: ResMut<NextState<GameState>>,
+    theme_handle: Res<ThemeHandle>,
+    themes: Res<Assets<Theme>>,
+    audio_player: Option<Res<Audio>>,
+    asset_server: Res<AssetServer>,
+    mut power_ups: ResMut<PowerUps>,
+) {
+    let mut broken_color = Color::srgb(1.0, 1.0, 0.2);
+    let mut final_color = Color::srgb(1.0, 0.2, 0.2);
+    let mut sound_path = None;
+
+    if let Some(theme) = themes.get(&theme_handle.0) {
+        broken_color = theme.player_broken_color.to_color();
+        final_color = theme.player_final_color.to_color();
+        if !theme.bone_break_path.is_empty() {
+            sound_path = Some(format!("themes/{}", theme.bone_break_path));
+        }
+    }
+
+    for collision_event in collision_events.read() {
+        let (collider1, collider2, started) = match collision_event {
+            CollisionEvent::Started(collider1, collider2, _) => (*collider1, *collider2, true),
+            CollisionEvent::Stopped(collider1, collider2, _) => (*collider1, *collider2, false),
+        };
+
+        // the hazard and the body part can be in either order
+        let Some((hazard_entity, hazard, hazard_transform, part, part_name, part_fatal)) =
+            [(collider1, collider2), (collider2, collider1)]
+                .into_iter()
+                .find_map(|(hazard_entity, part)| {
+                    let (hazard, hazard_transform) = hazard_query.get(hazard_entity).ok()?;
+                    let (part_name, body_part, ..) = part_query.get(part).ok()?;
+                    Some((
+                        hazard_entity,
+                        *hazard,
+                        hazard_transform,
+                        part,
+                        part_name.as_str(),
+                        body_part.fatal,
+                    ))
+                })
+        else {
+            continue;
+        };
+
+        match (hazard, started) {
+            (Hazard::SlowZone { .. }, true) => {
+                overlaps.0.entry(part).or_default().insert(hazard_entity);
+            }
+            (Hazard::SlowZone { .. }, false) => {
+                if let Some(zones) = overlaps.0.get_mut(&part) {
+                    zones.remove(&hazard_entity);
+                }
+            }
+            (Hazard::Sticky { duration }, true) => {
+                let Ok((_, _, part_transform, false)) = part_query.get(part) else {
+                    continue;
+                };
+
+                // pins the part where it touched, relative to the surface so moving ones drag it along
+                let surface_angle = hazard_transform.rotation.to_euler(EulerRot::XYZ).2;
+                let part_angle = part_transform.rotation.to_euler(EulerRot::XYZ).2;
+                let anchor = hazard_transform.rotation.inverse()
+                    * (part_transform.translation - hazard_transform.translation);
+                let joint = FixedJointBuilder::new()
+                    .local_anchor1(anchor.truncate())
+                    .local_basis1(part_angle - surface_angle);
+                let joint = commands
+                    .spawn((
+                        ImpulseJoint::new(hazard_entity, joint),
+                        Name::new("sticky_joint"),
+                    ))
+                    .id();
+
+                commands.entity(part).add_child(joint).insert((
+                    Stuck {
+                        joint,
+                        remaining: duration,
+                    },
+                    Velocity::zero(),
+                ));
+            }
+            (Hazard::Spikes, true) => {
+                if power_ups.absorb_hit() {
+                    continue;
+                }
+
+                // a broken part or a fatal one does not survive spikes
+                let fatal = part_fatal || player_data.broken_parts.contains(part_name);
+                player_data.broken_parts.insert(part_name.to_string());
+
+                if let Ok(material_handle) = color_query.get(part)
+                    && let Some(material) = materials.get_mut(&material_handle.0)
+                {
+                    material.color = if fatal { final_color } else { broken_color };
+                }
+                if let (Some(audio_player), Some(sound_path)) = (&audio_player, &sound_path) {
+                    audio_player.play(asset_server.load(sound_path));
+                }
+
+                if fatal {
+                    next_state.set(GameState::GameOver);
+                    player_data.last_death_str = "You fell onto spikes.".to_string();
+                    info!("Player died on spikes.");
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn release_stuck_parts(
+    mut commands: Commands,
+    mut stuck_query: Query<(Entity, &mut Stuck)>,
+    time: Res<Time>,
+) {
+    for (entity, mut stuck) in stuck_query.iter_mut() {
+        stuck.remaining -= time.delta_secs();
+        if stuck.remaining <= 0.0 {
+            commands.entity(stuck.joint).despawn();
+            commands.entity(entity).remove::<Stuck>();
+        }
+    }
+}
+
+pub fn apply_slow_zones(
+    mut overlaps: ResMut<SlowZoneOverlaps>,
+    hazard_query: Query<&Hazard>,
+    mut part_query: Query<(Entity, &mut Damping), With<PlayerBodyPart>>,
+) {
+    for (part, mut damping) in part_query.iter_mut() {
+        let mut zone_damping = 0.0_f32;
+        if let Some(zones) = overlaps.0.get_mut(&part) {
+            // despawned zones do not always send a stop event
+            zones.retain(|zone| hazard_query.contains(*zone));
+            for zone in zones.iter() {
+                if let Ok(Hazard::SlowZone { damping }) = hazard_query.get(*zone) {
+                    zone_damping = zone_damping.max(*damping);
+                }
+            }
+        }
+
+        let linear = PLAYER_LINEAR_DAMPING.max(zone_damping);
+        let angular = PLAYER_ANGULAR_DAMPING.max(zone_damping);
+        // only touches the component when the damping changes
+        if damping.linear_damping != linear || damping.angular_damping != angular {
+            damping.linear_damping = linear;
+            damping.angular_damping = angular;
+        }
+    }
+}
+
+/// A bar with a row of spikes on the top and bottom, filling the size.
+pub fn spikes_mesh(size: Vec2) -> Mesh {
+    let half = size / 2.0;
+    // half height of the solid bar in the middle
+    let bar = size.y * 0.2;
+    let count = ((size.x / SPIKE_WIDTH).round() as usize).max(1);
+    let spike_width = size.x / count as f32;
+
+    let mut points = vec![
+        Vec2::new(-half.x, -bar),
+        Vec2::new(half.x, -bar),
+        Vec2::new(half.x, bar),
+        Vec2::new(-half.x, -bar),
+        Vec2::new(half.x, bar),
+        Vec2::new(-half.x, bar),
+    ];
+    for index in 0..count {
+        let left = -half.x + index as f32 * spike_width;
+        let right = left + spike_width;
+        let middle = left + spike_width / 2.0;
+        points.extend([
+            Vec2::new(left, bar),
+            Vec2::new(right, bar),
+            Vec2::new(middle, half.y),
+            Vec2::new(left, -bar),
+            Vec2::new(middle, -half.y),
+            Vec2::new(right, -bar),
+        ]);
+    }
+
+    let positions: Vec<[f32; 3]> = points.iter().map(|point| [point.x, point.y, 0.0]).collect();
+    let uvs: Vec<[f32; 2]> = points
+        .iter()
+        .map(|point| [point.x / size.x + 0.5, 0.5 - point.y / size.y])
+        .collect();
+
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+}
@@ -0,0 +1,100 @@
+use std::time::Duration;
+
+use bevy::input::InputPlugin;
+use bevy::log::LogPlugin;
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+use bevy::time::TimeUpdateStrategy;
+use bevy::transform::TransformPlugin;
+use bevy_rapier2d::prelude::*;
+
+use crate::{TICK_RATE, game_states::GameState, player::PlayerData, seed::RunSeed};
+
+/// Replaces `DefaultPlugins` when running without a window, renderer or audio output.
+/// Time advances by exactly one tick per update and the game loop runs as fast as possible.
+/// Exits once the first run is over, printing the score and death reason.
+/// Use together with `FallingPlugin::start_in_game` to start the run right away.
+pub struct HeadlessPlugin {
+    pub tick_rate: f64,
+    // run is stopped after this many seconds of game time, if set
+    pub time_limit: Option<f32>,
+}
+
+impl Default for HeadlessPlugin {
+    fn default() -> Self {
+        Self {
+            tick_rate: TICK_RATE,
+            time_limit: None,
+        }
+    }
+}
+
+#[derive(Resource)]
+struct HeadlessTimeLimit(Option<f32>);
+
+// elapsed time when the run started, so loading does not count towards the time limit
+#[derive(Resource, Default)]
+struct HeadlessRunStart(f32);
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        let tick = Duration::from_secs_f64(1.0 / self.tick_rate);
+
+        app.add_plugins((
+            MinimalPlugins,
+            LogPlugin::default(),
+            AssetPlugin::default(),
+            TransformPlugin,
+            StatesPlugin,
+            InputPlugin,
+        ))
+        .init_asset::<Mesh>()
+        .init_asset::<ColorMaterial>()
+        .init_asset::<Image>()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(tick))
+        .insert_resource(Time::<Fixed>::from_duration(tick))
+        .insert_resource(TimestepMode::Fixed {
+            dt: tick.as_secs_f32(),
+            substeps: 1,
+        })
+        .insert_resource(HeadlessTimeLimit(self.time_limit))
+        .init_resource::<HeadlessRunStart>()
+        .add_systems(OnEnter(GameState::InGame), mark_run_start)
+        .add_systems(Update, check_time_limit.run_if(in_state(GameState::InGame)))
+        .add_systems(OnEnter(GameState::GameOver), report_headless_run);
+    }
+}
+
+fn mark_run_start(time: Res<Time>, mut run_start: ResMut<HeadlessRunStart>) {
+    run_start.0 = time.elapsed_secs();
+}
+
+fn check_time_limit(
+    time: Res<Time>,
+    time_limit: Res<HeadlessTimeLimit>,
+    run_start: Res<HeadlessRunStart>,
+    mut player_data: ResMut<PlayerData>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if let Some(limit) = time_limit.0
+        && time.elapsed_secs() - run_start.0 >= limit
+    {
+        next_state.set(GameState::GameOver);
+        player_data.last_death_str = "Time limit reached.".to_string();
+        info!("Headless run reached time limit.");
+    }
+}
+
+fn report_headless_run(
+    player_data: Res<PlayerData>,
+    run_seed: Res<RunSeed>,
+    time: Res<Time>,
+    run_start: Res<HeadlessRunStart>,
+    mut app_exit: EventWriter<AppExit>,
+) {
+    println!("Score: {}", player_data.score);
+    println!("Death reason: {}", player_data.last_death_str);
+    println!("Seed: {}", run_seed.seed);
+    println!("Game time: {:.2}s", time.elapsed_secs() - run_start.0);
+    app_exit.write(AppExit::Success);
+}
@@ -1,8 +1,14 @@
 use bevy::prelude::*;
 use bevy_kira_audio::prelude::*;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
-use crate::themes::{CurrentThemeIndex, ThemeManifest, ThemeManifestHandle};
+use crate::{
+    actions::{Action, ActionInput},
+    game_states::{GameState, NewRun},
+    seed::{RunSeed, roll_run_seed},
+    themes::{CurrentThemeIndex, ThemeManifest, ThemeManifestHandle},
+};
 
 #[derive(Component)]
 pub struct Jumpscare;
@@ -12,14 +18,40 @@ pub struct JumpscareActivated {
     activated: bool,
 }
 
+#[derive(Resource)]
+pub struct JumpscareRng(pub StdRng);
+
+pub struct JumpscarePlugin;
+
+impl Plugin for JumpscarePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_jumpscare)
+            .add_systems(NewRun, seed_jumpscare.after(roll_run_seed))
+            .add_systems(
+                FixedUpdate,
+                activate_jumpscare.run_if(not(in_state(GameState::Paused))),
+            )
+            .add_systems(Update, despawn_jumpscare);
+    }
+}
+
+const TICK_JUMPSCARE_CHANCE: f64 = 0.0006;
+
 pub fn setup_jumpscare(mut commands: Commands) {
     commands.insert_resource(JumpscareActivated { activated: false });
+    commands.insert_resource(JumpscareRng(StdRng::from_entropy()));
+}
+
+pub fn seed_jumpscare(mut jumpscare_rng: ResMut<JumpscareRng>, run_seed: Res<RunSeed>) {
+    // offset so the jumpscare does not roll the same numbers as the obstacles
+    jumpscare_rng.0 = StdRng::seed_from_u64(run_seed.seed.wrapping_add(1));
 }
 
 pub fn activate_jumpscare(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut jumpscare_act: ResMut<JumpscareActivated>,
+    mut jumpscare_rng: ResMut<JumpscareRng>,
     audio_player: Res<Audio>,
     current_index: Res<CurrentThemeIndex>,
     manifest_handle: Res<ThemeManifestHandle>,
@@ -32,8 +64,7 @@ pub fn activate_jumpscare(
             return;
         }
 
-        let mut rng = rand::thread_rng();
-        if !rng.gen_bool(0.0006) {
+        if !jumpscare_rng.0.gen_bool(TICK_JUMPSCARE_CHANCE) {
             return;
         }
 
@@ -62,11 +93,12 @@ pub fn activate_jumpscare(
 
 pub fn despawn_jumpscare(
     mut commands: Commands,
-    kb_input: Res<ButtonInput<KeyCode>>,
+    action_input: Res<ActionInput>,
     jumpscare_act: Res<JumpscareActivated>,
     jumpscare_ents: Query<Entity, With<Jumpscare>>,
 ) {
-    if kb_input.just_pressed(KeyCode::Escape) && jumpscare_act.activated {
+    let dismiss = action_input.just_pressed(Action::Dismiss) || action_input.tapped();
+    if dismiss && jumpscare_act.activated {
         for jumpscare_ent in jumpscare_ents.iter() {
             commands.entity(jumpscare_ent).despawn();
         }
@@ -0,0 +1,246 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game_modes::{GameMode, RunStart},
+    game_states::GameState,
+    player::{PlayerData, WorldShift},
+    player_setup::PlayerTorso,
+    seed::RunSeed,
+    themes::{CurrentThemeIndex, Theme, ThemeHandle, ThemeManifest},
+    zones::depth_at,
+};
+
+const LEADERBOARD_SIZE: usize = 10;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LeaderboardEntry {
+    pub score: i32,
+    // in meters
+    pub depth: f32,
+    // in seconds
+    pub duration: f32,
+    pub death: String,
+    pub theme: String,
+    pub seed: u64,
+    #[serde(default)]
+    pub mode: GameMode,
+}
+
+impl LeaderboardEntry {
+    /// Single line summary, as shown on the game over screen and in the main menu.
+    pub fn summary(&self, position: usize) -> String {
+        let minutes = self.duration as u32 / 60;
+        let seconds = self.duration as u32 % 60;
+
+        format!(
+            "{:>2}. {:>6}  {:>5.0}m  {}:{:02}  {}  {}  Seed {}  {}",
+            position + 1,
+            self.score,
+            self.depth,
+            minutes,
+            seconds,
+            self.mode.name(),
+            self.theme,
+            self.seed,
+            self.death
+        )
+    }
+}
+
+/// Best local runs, sorted by score.
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Leaderboard {
+    pub entries: Vec<LeaderboardEntry>,
+    // position of the last run, if it made it onto the leaderboard
+    #[serde(skip)]
+    pub latest: Option<usize>,
+}
+
+impl Leaderboard {
+    pub fn path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("falling").join("leaderboard.ron"))
+    }
+
+    /// Loads the leaderboard, starting with an empty one if it is missing or corrupt.
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else {
+            return Self::default();
+        };
+        if !path.exists() {
+            return Self::default();
+        }
+
+        match Self::read(&path) {
+            Ok(leaderboard) => leaderboard,
+            Err(err) => {
+                warn!(
+                    "Failed to read leaderboard from {}: {}",
+                    path.display(),
+                    err
+                );
+                Self::default()
+            }
+        }
+    }
+
+    fn read(path: &Path) -> Result<Self, Box<dyn Error>> {
+        Ok(ron::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let path = Self::path().ok_or("no data directory found")?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(
+            path,
+            ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?,
+        )?;
+        Ok(())
+    }
+
+    /// Adds a run and returns its position if it made it onto the leaderboard.
+    pub fn insert(&mut self, entry: LeaderboardEntry) -> Option<usize> {
+        let position = self
+            .entries
+            .iter()
+            .position(|other| entry.score > other.score)
+            .unwrap_or(self.entries.len());
+
+        self.latest = if position < LEADERBOARD_SIZE {
+            self.entries.insert(position, entry);
+            self.entries.truncate(LEADERBOARD_SIZE);
+            Some(position)
+        } else {
+            None
+        };
+        self.latest
+    }
+}
+
+#[derive(Component)]
+pub struct LeaderboardText;
+
+pub struct LeaderboardPlugin;
+
+impl Plugin for LeaderboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Leaderboard::load())
+            .add_systems(
+                OnEnter(GameState::GameOver),
+                (record_run, spawn_leaderboard_ui).chain(),
+            )
+            .add_systems(OnExit(GameState::GameOver), despawn_leaderboard_ui);
+    }
+}
+
+pub fn record_run(
+    mut leaderboard: ResMut<Leaderboard>,
+    player_data: Res<PlayerData>,
+    run_seed: Res<RunSeed>,
+    run_start: Res<RunStart>,
+    current_index: Res<CurrentThemeIndex>,
+    torso_query: Query<&Transform, With<PlayerTorso>>,
+    world_shift: Res<WorldShift>,
+    mode: Res<GameMode>,
+    time: Res<Time>,
+) {
+    let depth = torso_query
+        .single()
+        .map_or(0.0, |torso| depth_at(torso.translation.y, &world_shift));
+    let theme = ThemeManifest::builtin()
+        .themes
+        .get(current_index.0)
+        .map_or("Default".to_string(), |theme| theme.name.clone());
+
+    let position = leaderboard.insert(LeaderboardEntry {
+        score: player_data.score,
+        depth: depth.max(0.0),
+        duration: time.elapsed_secs() - run_start.0,
+        death: player_data.last_death_str.clone(),
+        theme,
+        seed: run_seed.seed,
+        mode: *mode,
+    });
+
+    if position.is_some() {
+        match leaderboard.save() {
+            Ok(()) => info!("Saved leaderboard."),
+            Err(err) => warn!("Failed to save leaderboard: {}", err),
+        }
+    }
+}
+
+pub fn spawn_leaderboard_ui(
+    mut commands: Commands,
+    leaderboard: Res<Leaderboard>,
+    theme_handle: Res<ThemeHandle>,
+    themes: Res<Assets<Theme>>,
+) {
+    let mut text_color = Color::srgb(1.0, 1.0, 1.0);
+    let mut highlight_color = Color::srgb(1.0, 1.0, 0.2);
+
+    if let Some(theme) = themes.get(&theme_handle.0) {
+        text_color = theme.text_color.to_color();
+        highlight_color = theme.player_broken_color.to_color();
+    }
+
+    let title = if leaderboard.latest == Some(0) {
+        "New personal best!"
+    } else {
+        "High Scores"
+    };
+
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                bottom: Val::Px(20.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            LeaderboardText,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(title),
+                TextFont {
+                    font_size: 22.0,
+                    ..default()
+                },
+                TextColor(if leaderboard.latest == Some(0) {
+                    highlight_color
+                } else {
+                    text_color
+                }),
+            ));
+
+            for (i, entry) in leaderboard.entries.iter().enumerate() {
+                parent.spawn((
+                    Text::new(entry.summary(i)),
+                    TextFont {
+                        font_size: 16.0,
+                        ..default()
+                    },
+                    TextColor(if leaderboard.latest == Some(i) {
+                        highlight_color
+                    } else {
+                        text_color
+                    }),
+                ));
+            }
+        });
+}
+
+pub fn despawn_leaderboard_ui(mut commands: Commands, query: Query<Entity, With<LeaderboardText>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
@@ -0,0 +1,182 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+pub mod actions;
+pub mod audio;
+pub mod camera;
+pub mod cli;
+pub mod controls_menu;
+pub mod environment;
+pub mod game_modes;
+pub mod game_states;
+pub mod ghost;
+pub mod hazards;
+pub mod headless;
+pub mod jumpscare;
+pub mod leaderboard;
+pub mod main_menu;
+pub mod menu;
+pub mod obstacles;
+pub mod pause;
+pub mod pickups;
+pub mod player;
+pub mod player_setup;
+pub mod replay;
+pub mod seed;
+pub mod segments;
+pub mod settings;
+pub mod shaft;
+pub mod skeleton;
+pub mod themes;
+pub mod time_scale;
+pub mod ui;
+pub mod zones;
+
+/// Fixed ticks per second that gameplay and physics run at, independent of the frame rate.
+pub const TICK_RATE: f64 = 60.0;
+
+use actions::ActionsPlugin;
+use audio::GameAudioPlugin;
+use camera::FollowCameraPlugin;
+use controls_menu::ControlsMenuPlugin;
+use environment::EnvironmentPlugin;
+use game_modes::GameModePlugin;
+use game_states::GameStatePlugin;
+use ghost::GhostPlugin;
+use hazards::HazardPlugin;
+use jumpscare::JumpscarePlugin;
+use leaderboard::LeaderboardPlugin;
+use main_menu::MainMenuPlugin;
+use menu::MenuPlugin;
+use obstacles::ObstacleCatalogPlugin;
+use pause::PausePlugin;
+use pickups::PickupPlugin;
+use player::PlayerPlugin;
+use replay::{Replay, ReplayPlugin};
+use seed::SeedPlugin;
+use segments::SegmentPlugin;
+use settings::{Settings, SettingsPlugin};
+use skeleton::SkeletonPlugin;
+use themes::ThemePlugin;
+use time_scale::TimeScalePlugin;
+use ui::HudPlugin;
+use zones::ZonePlugin;
+
+// TODO: add sound effects
+
+/// Adds the whole game to an app.
+/// Expects `DefaultPlugins` (or `HeadlessPlugin` when headless) to already be added.
+#[derive(Default)]
+pub struct FallingPlugin {
+    // skips camera, audio and ui when true
+    pub headless: bool,
+    // every run uses this seed if set, otherwise the seed from the settings or a new one for each run
+    pub seed: Option<u64>,
+    // plays back this replay instead of reading the keyboard
+    pub replay: Option<Replay>,
+    // saves replays of headless runs, which are not saved by default
+    pub record: bool,
+    // name of the theme to start with, overrides the settings
+    pub theme: Option<String>,
+    // name of the character to play as, overrides the settings
+    pub character: Option<String>,
+    // mutes audio without changing the volume setting
+    pub muted: bool,
+    // skips the start screen
+    pub start_in_game: bool,
+}
+
+impl Plugin for FallingPlugin {
+    fn build(&self, app: &mut App) {
+        // the headless plugin sets up its own tick
+        if !app.world().contains_resource::<TimestepMode>() {
+            app.insert_resource(Time::<Fixed>::from_hz(TICK_RATE))
+                .insert_resource(TimestepMode::Fixed {
+                    dt: (1.0 / TICK_RATE) as f32,
+                    substeps: 1,
+                });
+        }
+        if !app.is_plugin_added::<RapierPhysicsPlugin<NoUserData>>() {
+            app.add_plugins(
+                RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0).in_fixed_schedule(),
+            );
+        }
+
+        // headless runs do not depend on the settings of the machine they run on
+        let settings = if self.headless {
+            Settings::default()
+        } else {
+            Settings::load()
+        };
+
+        app.add_plugins((
+            GameStatePlugin {
+                start_in_game: self.start_in_game || self.headless || self.replay.is_some(),
+            },
+            SeedPlugin {
+                seed: self
+                    .replay
+                    .as_ref()
+                    .map(|replay| replay.seed)
+                    .or(self.seed)
+                    .or(settings.seed),
+            },
+            GameModePlugin {
+                mode: self
+                    .replay
+                    .as_ref()
+                    .map_or(settings.game_mode, |replay| replay.mode),
+            },
+            ThemePlugin {
+                theme: self.theme.clone().or(Some(settings.theme.clone())),
+            },
+            SkeletonPlugin {
+                // replays always use the character they were recorded with
+                character: match &self.replay {
+                    Some(replay) => Some(replay.character.clone()).filter(|name| !name.is_empty()),
+                    None => self.character.clone().or(Some(settings.character.clone())),
+                },
+            },
+            ActionsPlugin,
+            TimeScalePlugin,
+            PlayerPlugin,
+            ObstacleCatalogPlugin,
+            SegmentPlugin,
+            EnvironmentPlugin,
+            HazardPlugin,
+            PickupPlugin {
+                headless: self.headless,
+            },
+            ZonePlugin {
+                headless: self.headless,
+            },
+            ReplayPlugin {
+                playback: self.replay.clone(),
+                save: !self.headless || self.record,
+            },
+        ));
+
+        if !self.headless {
+            app.add_plugins((
+                FollowCameraPlugin,
+                GameAudioPlugin {
+                    volume: if self.muted { 0.0 } else { settings.volume },
+                },
+                HudPlugin,
+                JumpscarePlugin,
+                MenuPlugin,
+                MainMenuPlugin,
+                ControlsMenuPlugin,
+                PausePlugin,
+            ));
+
+            if self.replay.is_none() {
+                app.add_plugins((GhostPlugin, LeaderboardPlugin));
+            }
+
+            app.add_plugins(SettingsPlugin);
+        }
+
+        app.insert_resource(settings);
+    }
+}
@@ -1,116 +1,104 @@
-use std::collections::HashSet;
-
-// use bevy::dev_tools::fps_overlay::FpsOverlayPlugin;
+use bevy::dev_tools::fps_overlay::FpsOverlayPlugin;
 use bevy::prelude::*;
-use bevy_common_assets::ron::RonAssetPlugin;
+use bevy::window::{MonitorSelection, WindowMode, WindowResolution};
 use bevy_embedded_assets::{EmbeddedAssetPlugin, PluginMode};
-use bevy_kira_audio::AudioPlugin;
 use bevy_rapier2d::prelude::*;
-use rand::SeedableRng;
-use rand::rngs::StdRng;
+use clap::Parser;
+use falling::FallingPlugin;
+use falling::cli::Args;
+use falling::headless::HeadlessPlugin;
+use falling::replay::Replay;
+use falling::skeleton::CharacterManifest;
+use falling::themes::ThemeManifest;
 
-mod player;
-use player::*;
+fn main() {
+    let args = Args::parse();
+    let manifest = ThemeManifest::builtin();
+    let characters = CharacterManifest::builtin();
 
-mod camera;
-use camera::*;
+    if args.list_themes {
+        for theme in &manifest.themes {
+            println!("{}", theme.name);
+        }
+        return;
+    }
 
-mod environment;
-use environment::*;
+    if args.list_characters {
+        for character in &characters.characters {
+            println!("{}", character.name);
+        }
+        return;
+    }
 
-mod player_setup;
-use player_setup::*;
+    if let Some(theme) = &args.theme
+        && manifest.find(theme).is_none()
+    {
+        eprintln!("Unknown theme '{theme}'. Use --list-themes to see all themes.");
+        std::process::exit(1);
+    }
 
-mod game_states;
-use game_states::*;
+    if let Some(character) = &args.character
+        && characters.find(character).is_none()
+    {
+        eprintln!("Unknown character '{character}'. Use --list-characters to see all characters.");
+        std::process::exit(1);
+    }
 
-mod ui;
-use ui::*;
+    let replay = match args.replay.as_deref().map(Replay::load).transpose() {
+        Ok(replay) => replay,
+        Err(err) => {
+            eprintln!("Failed to load replay: {err}");
+            std::process::exit(1);
+        }
+    };
 
-mod themes;
-use themes::*;
+    let mut app = App::new();
+    app.add_plugins(EmbeddedAssetPlugin {
+        mode: PluginMode::ReplaceDefault,
+    });
 
-mod audio;
-use audio::*;
+    if args.headless {
+        app.add_plugins(HeadlessPlugin {
+            time_limit: args.time_limit,
+            ..default()
+        });
+    } else {
+        let mut window = Window {
+            title: "Falling".into(),
+            ..default()
+        };
+        if args.width.is_some() || args.height.is_some() {
+            window.resolution = WindowResolution::new(
+                args.width.unwrap_or(window.resolution.width()),
+                args.height.unwrap_or(window.resolution.height()),
+            );
+        }
+        if args.fullscreen {
+            window.mode = WindowMode::BorderlessFullscreen(MonitorSelection::Current);
+        }
 
-mod jumpscare;
-use jumpscare::*;
+        app.add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(window),
+            ..default()
+        }));
 
-// TODO: add sound effects
+        if args.fps {
+            app.add_plugins(FpsOverlayPlugin::default());
+        }
+        if args.debug_physics {
+            app.add_plugins(RapierDebugRenderPlugin::default());
+        }
+    }
 
-fn main() {
-    App::new()
-        .add_plugins(EmbeddedAssetPlugin {
-            mode: PluginMode::ReplaceDefault,
-        })
-        .add_plugins(DefaultPlugins.set(WindowPlugin {
-            primary_window: Some(Window {
-                title: "Falling".into(),
-                ..default()
-            }),
-            ..default()
-        }))
-        .add_plugins(AudioPlugin)
-        .add_plugins(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
-        .add_plugins((
-            RonAssetPlugin::<Theme>::new(&["theme.ron"]),
-            RonAssetPlugin::<ThemeManifest>::new(&[".ron"]),
-        ))
-        // .add_plugins(FpsOverlayPlugin::default())
-        // .add_plugins(RapierDebugRenderPlugin::default())
-        .insert_resource(ObstaclesData {
-            last_spawned: 0.0,
-            rng: StdRng::from_entropy(),
-        })
-        .insert_resource(PlayerData {
-            broken_parts: HashSet::new(),
-            last_death_str: "".to_string(),
-            last_y_position: 200.0,
-            score: 0,
-        })
-        .insert_resource(ThemeInfo { loaded: false })
-        .insert_resource(CurrentThemeIndex(0))
-        .insert_resource(JustLoadedTheme(false))
-        .insert_state(GameState::PreGame)
-        .add_systems(PreStartup, load_themes_manifest)
-        .add_systems(PreStartup, load_theme)
-        .add_systems(Startup, setup_environment)
-        .add_systems(Startup, setup_player)
-        .add_systems(Startup, setup_camera)
-        .add_systems(Startup, play_background_audio)
-        .add_systems(Startup, setup_jumpscare)
-        .add_systems(PostStartup, spawn_score_ui)
-        .add_systems(PostStartup, show_keybindings)
-        .add_systems(PostStartup, show_current_theme)
-        .add_systems(OnEnter(GameState::PreGame), spawn_pre_game_ui)
-        .add_systems(OnExit(GameState::PreGame), despawn_pre_game_ui)
-        .add_systems(OnExit(GameState::PreGame), setup_player)
-        .add_systems(
-            Update,
-            handle_pre_game_input.run_if(in_state(GameState::PreGame)),
-        )
-        .add_systems(PreUpdate, check_theme)
-        .add_systems(Update, activate_jumpscare)
-        .add_systems(Update, despawn_jumpscare)
-        .add_systems(Update, cycle_theme)
-        .add_systems(Update, update_music)
-        .add_systems(Update, player_control.run_if(in_state(GameState::InGame)))
-        .add_systems(Update, recenter_world)
-        .add_systems(Update, manage_obstacles.run_if(in_state(GameState::InGame)))
-        .add_systems(Update, handle_collision.run_if(in_state(GameState::InGame)))
-        .add_systems(Update, update_theme)
-        .add_systems(OnEnter(GameState::GameOver), spawn_game_over_ui)
-        .add_systems(OnExit(GameState::GameOver), despawn_game_over_ui)
-        .add_systems(OnExit(GameState::GameOver), setup_player)
-        .add_systems(
-            Update,
-            handle_game_over_input.run_if(in_state(GameState::GameOver)),
-        )
-        .add_systems(
-            PostUpdate,
-            increment_score.run_if(in_state(GameState::InGame)),
-        )
-        .add_systems(PostUpdate, update_score_ui)
-        .add_systems(PostUpdate, camera_follow_y)
-        .run();
+    app.add_plugins(FallingPlugin {
+        headless: args.headless,
+        seed: args.seed,
+        replay,
+        record: args.record,
+        theme: args.theme,
+        character: args.character,
+        muted: args.mute,
+        start_in_game: args.play,
+    })
+    .run();
 }
@@ -0,0 +1,514 @@
+use bevy::prelude::*;
+
+use crate::{
+    actions::{Action, ActionInput},
+    game_modes::GameMode,
+    game_states::GameState,
+    leaderboard::Leaderboard,
+    menu::{
+        MenuButton, MenuPressed, MenuSelection, MenuState, despawn_menu, menu_button,
+        navigate_menu, spawn_menu, spawn_menu_root,
+    },
+    skeleton::{CharacterManifest, CurrentCharacterIndex, skeleton_loaded},
+    themes::{CurrentThemeIndex, Theme, ThemeHandle, ThemeManifest},
+};
+
+#[derive(Component, Clone, Copy)]
+pub enum MainMenuAction {
+    Play,
+    GameModes,
+    Characters,
+    Themes,
+    Settings,
+    HighScores,
+    Quit,
+}
+
+#[derive(Component, Clone, Copy)]
+pub enum GameModeAction {
+    Select(GameMode),
+    Back,
+}
+
+#[derive(Component, Clone, Copy)]
+pub enum CharacterAction {
+    // index into the character manifest
+    Select(usize),
+    Back,
+}
+
+#[derive(Component, Clone, Copy)]
+pub enum ThemeAction {
+    // index into the theme manifest
+    Select(usize),
+    Back,
+}
+
+#[derive(Component, Clone, Copy)]
+pub struct HighScoresBack;
+
+#[derive(Clone, Copy)]
+pub enum SwatchPart {
+    Background,
+    Walls,
+    Obstacles,
+    Player,
+    Text,
+}
+
+// part of the color preview of a theme in the theme picker
+#[derive(Component)]
+pub struct ThemeSwatch {
+    pub theme: usize,
+    pub part: SwatchPart,
+}
+
+#[derive(Component)]
+pub struct CurrentThemeMarker(pub usize);
+
+// every theme of the manifest, loaded for the previews of the theme picker
+#[derive(Resource, Default)]
+pub struct ThemePreviews(pub Vec<Handle<Theme>>);
+
+pub struct MainMenuPlugin;
+
+impl Plugin for MainMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ThemePreviews>()
+            .add_systems(OnEnter(GameState::PreGame), open_main_menu)
+            .add_systems(OnExit(GameState::PreGame), close_main_menu)
+            .add_systems(OnEnter(MenuState::Main), spawn_main_menu)
+            .add_systems(OnExit(MenuState::Main), despawn_menu)
+            .add_systems(OnEnter(MenuState::GameModes), spawn_game_modes_menu)
+            .add_systems(OnExit(MenuState::GameModes), despawn_menu)
+            .add_systems(OnEnter(MenuState::Characters), spawn_characters_menu)
+            .add_systems(OnExit(MenuState::Characters), despawn_menu)
+            .add_systems(
+                OnEnter(MenuState::Themes),
+                (load_theme_previews, spawn_themes_menu),
+            )
+            .add_systems(OnExit(MenuState::Themes), despawn_menu)
+            .add_systems(OnEnter(MenuState::HighScores), spawn_high_scores_menu)
+            .add_systems(OnExit(MenuState::HighScores), despawn_menu)
+            .add_systems(
+                Update,
+                (
+                    // play waits for the skeleton of the selected character
+                    handle_main_menu.run_if(in_state(MenuState::Main).and(skeleton_loaded)),
+                    handle_game_modes_menu.run_if(in_state(MenuState::GameModes)),
+                    handle_characters_menu.run_if(in_state(MenuState::Characters)),
+                    (handle_themes_menu, update_theme_swatches)
+                        .chain()
+                        .run_if(in_state(MenuState::Themes)),
+                    handle_high_scores_menu.run_if(in_state(MenuState::HighScores)),
+                )
+                    .after(navigate_menu),
+            );
+    }
+}
+
+fn menu_text_color(theme_handle: &ThemeHandle, themes: &Assets<Theme>) -> Color {
+    themes
+        .get(&theme_handle.0)
+        .map_or(Color::srgb(1.0, 1.0, 1.0), |theme| {
+            theme.text_color.to_color()
+        })
+}
+
+pub fn open_main_menu(mut next_menu: ResMut<NextState<MenuState>>) {
+    next_menu.set(MenuState::Main);
+}
+
+pub fn close_main_menu(mut next_menu: ResMut<NextState<MenuState>>) {
+    next_menu.set(MenuState::Closed);
+}
+
+pub fn spawn_main_menu(
+    mut commands: Commands,
+    theme_handle: Res<ThemeHandle>,
+    themes: Res<Assets<Theme>>,
+) {
+    let items = [
+        ("Play".to_string(), MainMenuAction::Play),
+        ("Game Modes".to_string(), MainMenuAction::GameModes),
+        ("Characters".to_string(), MainMenuAction::Characters),
+        ("Themes".to_string(), MainMenuAction::Themes),
+        ("Settings".to_string(), MainMenuAction::Settings),
+        ("High Scores".to_string(), MainMenuAction::HighScores),
+        ("Quit".to_string(), MainMenuAction::Quit),
+    ];
+
+    spawn_menu(
+        &mut commands,
+        "Falling",
+        &items,
+        menu_text_color(&theme_handle, &themes),
+    );
+}
+
+pub fn handle_main_menu(
+    mut pressed_events: EventReader<MenuPressed>,
+    action_query: Query<&MainMenuAction>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut next_menu: ResMut<NextState<MenuState>>,
+    mut app_exit: EventWriter<AppExit>,
+) {
+    for event in pressed_events.read() {
+        match action_query.get(event.0) {
+            Ok(MainMenuAction::Play) => next_state.set(GameState::InGame),
+            Ok(MainMenuAction::GameModes) => next_menu.set(MenuState::GameModes),
+            Ok(MainMenuAction::Characters) => next_menu.set(MenuState::Characters),
+            Ok(MainMenuAction::Themes) => next_menu.set(MenuState::Themes),
+            Ok(MainMenuAction::Settings) => next_menu.set(MenuState::Settings),
+            Ok(MainMenuAction::HighScores) => next_menu.set(MenuState::HighScores),
+            Ok(MainMenuAction::Quit) => {
+                app_exit.write(AppExit::Success);
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+pub fn spawn_game_modes_menu(
+    mut commands: Commands,
+    mode: Res<GameMode>,
+    mut selection: ResMut<MenuSelection>,
+    theme_handle: Res<ThemeHandle>,
+    themes: Res<Assets<Theme>>,
+) {
+    let mut items: Vec<(String, GameModeAction)> = GameMode::ALL
+        .iter()
+        .map(|option| {
+            let marker = if option == &*mode { "> " } else { "" };
+            (
+                format!("{}{}: {}", marker, option.name(), option.description()),
+                GameModeAction::Select(*option),
+            )
+        })
+        .collect();
+    items.push(("Back".to_string(), GameModeAction::Back));
+
+    selection.0 = GameMode::ALL
+        .iter()
+        .position(|option| option == &*mode)
+        .unwrap_or_default();
+
+    spawn_menu(
+        &mut commands,
+        "Game Modes",
+        &items,
+        menu_text_color(&theme_handle, &themes),
+    );
+}
+
+pub fn handle_game_modes_menu(
+    action_input: Res<ActionInput>,
+    mut pressed_events: EventReader<MenuPressed>,
+    action_query: Query<&GameModeAction>,
+    mut mode: ResMut<GameMode>,
+    mut next_menu: ResMut<NextState<MenuState>>,
+) {
+    if action_input.just_pressed(Action::Dismiss) {
+        next_menu.set(MenuState::Main);
+        return;
+    }
+
+    for event in pressed_events.read() {
+        match action_query.get(event.0) {
+            Ok(GameModeAction::Select(selected)) => {
+                *mode = *selected;
+                info!("Selected game mode: {}", selected.name());
+                next_menu.set(MenuState::Main);
+            }
+            Ok(GameModeAction::Back) => next_menu.set(MenuState::Main),
+            Err(_) => {}
+        }
+    }
+}
+
+pub fn spawn_characters_menu(
+    mut commands: Commands,
+    current_index: Res<CurrentCharacterIndex>,
+    mut selection: ResMut<MenuSelection>,
+    theme_handle: Res<ThemeHandle>,
+    themes: Res<Assets<Theme>>,
+) {
+    let mut items: Vec<(String, CharacterAction)> = CharacterManifest::builtin()
+        .characters
+        .iter()
+        .enumerate()
+        .map(|(index, character)| {
+            let marker = if index == current_index.0 { "> " } else { "" };
+            (
+                format!("{}{}", marker, character.name),
+                CharacterAction::Select(index),
+            )
+        })
+        .collect();
+    items.push(("Back".to_string(), CharacterAction::Back));
+
+    selection.0 = current_index.0;
+
+    spawn_menu(
+        &mut commands,
+        "Characters",
+        &items,
+        menu_text_color(&theme_handle, &themes),
+    );
+}
+
+pub fn handle_characters_menu(
+    action_input: Res<ActionInput>,
+    mut pressed_events: EventReader<MenuPressed>,
+    action_query: Query<&CharacterAction>,
+    mut current_index: ResMut<CurrentCharacterIndex>,
+    mut next_menu: ResMut<NextState<MenuState>>,
+) {
+    if action_input.just_pressed(Action::Dismiss) {
+        next_menu.set(MenuState::Main);
+        return;
+    }
+
+    for event in pressed_events.read() {
+        match action_query.get(event.0) {
+            Ok(CharacterAction::Select(index)) => {
+                // the skeleton is loaded right away and used from the next run on
+                if current_index.0 != *index {
+                    current_index.0 = *index;
+                }
+                next_menu.set(MenuState::Main);
+            }
+            Ok(CharacterAction::Back) => next_menu.set(MenuState::Main),
+            Err(_) => {}
+        }
+    }
+}
+
+pub fn load_theme_previews(mut previews: ResMut<ThemePreviews>, asset_server: Res<AssetServer>) {
+    if previews.0.is_empty() {
+        previews.0 = ThemeManifest::builtin()
+            .themes
+            .iter()
+            .map(|theme| asset_server.load(&theme.path))
+            .collect();
+    }
+}
+
+pub fn spawn_themes_menu(
+    mut commands: Commands,
+    current_index: Res<CurrentThemeIndex>,
+    mut selection: ResMut<MenuSelection>,
+    theme_handle: Res<ThemeHandle>,
+    themes: Res<Assets<Theme>>,
+) {
+    let text_color = menu_text_color(&theme_handle, &themes);
+    let manifest = ThemeManifest::builtin();
+    let root = spawn_menu_root(&mut commands, "Themes", text_color);
+
+    commands.entity(root).with_children(|parent| {
+        for (index, theme) in manifest.themes.iter().enumerate() {
+            parent
+                .spawn((
+                    Button,
+                    Node {
+                        flex_direction: FlexDirection::Row,
+                        align_items: AlignItems::Center,
+                        column_gap: Val::Px(12.0),
+                        padding: UiRect::axes(Val::Px(16.0), Val::Px(4.0)),
+                        ..default()
+                    },
+                    MenuButton(index),
+                    ThemeAction::Select(index),
+                ))
+                .with_children(|row| {
+                    // the theme drawn in its own colors
+                    row.spawn((
+                        Node {
+                            width: Val::Px(240.0),
+                            height: Val::Px(36.0),
+                            flex_direction: FlexDirection::Row,
+                            align_items: AlignItems::Center,
+                            column_gap: Val::Px(6.0),
+                            padding: UiRect::horizontal(Val::Px(8.0)),
+                            ..default()
+                        },
+                        ThemeSwatch {
+                            theme: index,
+                            part: SwatchPart::Background,
+                        },
+                    ))
+                    .with_children(|preview| {
+                        for part in [SwatchPart::Walls, SwatchPart::Obstacles, SwatchPart::Player] {
+                            preview.spawn((
+                                Node {
+                                    width: Val::Px(18.0),
+                                    height: Val::Px(18.0),
+                                    ..default()
+                                },
+                                ThemeSwatch { theme: index, part },
+                            ));
+                        }
+
+                        preview.spawn((
+                            Text::new(theme.name.clone()),
+                            TextFont {
+                                font_size: 22.0,
+                                ..default()
+                            },
+                            TextColor(text_color),
+                            ThemeSwatch {
+                                theme: index,
+                                part: SwatchPart::Text,
+                            },
+                        ));
+                    });
+
+                    row.spawn((
+                        Text::new(""),
+                        TextFont {
+                            font_size: 18.0,
+                            ..default()
+                        },
+                        TextColor(text_color),
+                        Node {
+                            width: Val::Px(80.0),
+                            ..default()
+                        },
+                        CurrentThemeMarker(index),
+                    ));
+                });
+        }
+
+        parent.spawn(menu_button(
+            "Back",
+            manifest.themes.len(),
+            ThemeAction::Back,
+            text_color,
+        ));
+    });
+
+    selection.0 = current_index.0;
+}
+
+pub fn handle_themes_menu(
+    action_input: Res<ActionInput>,
+    mut pressed_events: EventReader<MenuPressed>,
+    action_query: Query<&ThemeAction>,
+    mut current_index: ResMut<CurrentThemeIndex>,
+    mut next_menu: ResMut<NextState<MenuState>>,
+) {
+    if action_input.just_pressed(Action::Dismiss) {
+        next_menu.set(MenuState::Main);
+        return;
+    }
+
+    for event in pressed_events.read() {
+        match action_query.get(event.0) {
+            Ok(ThemeAction::Select(index)) => {
+                if current_index.0 != *index {
+                    current_index.0 = *index;
+                }
+            }
+            Ok(ThemeAction::Back) => next_menu.set(MenuState::Main),
+            Err(_) => {}
+        }
+    }
+}
+
+pub fn update_theme_swatches(
+    previews: Res<ThemePreviews>,
+    themes: Res<Assets<Theme>>,
+    current_index: Res<CurrentThemeIndex>,
+    mut swatch_query: Query<(
+        &ThemeSwatch,
+        Option<&mut BackgroundColor>,
+        Option<&mut TextColor>,
+    )>,
+    mut marker_query: Query<(&CurrentThemeMarker, &mut Text)>,
+) {
+    for (swatch, background, text_color) in swatch_query.iter_mut() {
+        let Some(theme) = previews
+            .0
+            .get(swatch.theme)
+            .and_then(|handle| themes.get(handle))
+        else {
+            continue;
+        };
+
+        let color = match swatch.part {
+            SwatchPart::Background => theme.background_color.to_color(),
+            SwatchPart::Walls => theme.walls_color.to_color(),
+            SwatchPart::Obstacles => theme.obstacles_base_color.to_color(),
+            SwatchPart::Player => theme.player_body_color.to_color(),
+            SwatchPart::Text => theme.text_color.to_color(),
+        };
+
+        // text is recolored by update_theme whenever the current theme changes
+        if let Some(mut text_color) = text_color {
+            text_color.0 = color;
+        } else if let Some(mut background) = background {
+            background.0 = color;
+        }
+    }
+
+    for (marker, mut text) in marker_query.iter_mut() {
+        let label = if marker.0 == current_index.0 {
+            "Current"
+        } else {
+            ""
+        };
+        if text.0 != label {
+            text.0 = label.to_string();
+        }
+    }
+}
+
+pub fn spawn_high_scores_menu(
+    mut commands: Commands,
+    leaderboard: Option<Res<Leaderboard>>,
+    theme_handle: Res<ThemeHandle>,
+    themes: Res<Assets<Theme>>,
+) {
+    let text_color = menu_text_color(&theme_handle, &themes);
+    let root = spawn_menu_root(&mut commands, "High Scores", text_color);
+
+    let rows: Vec<String> = match &leaderboard {
+        Some(leaderboard) if !leaderboard.entries.is_empty() => leaderboard
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| entry.summary(i))
+            .collect(),
+        _ => vec!["No runs yet.".to_string()],
+    };
+
+    commands.entity(root).with_children(|parent| {
+        for row in rows {
+            parent.spawn((
+                Text::new(row),
+                TextFont {
+                    font_size: 16.0,
+                    ..default()
+                },
+                TextColor(text_color),
+            ));
+        }
+
+        parent.spawn(menu_button("Back", 0, HighScoresBack, text_color));
+    });
+}
+
+pub fn handle_high_scores_menu(
+    action_input: Res<ActionInput>,
+    mut pressed_events: EventReader<MenuPressed>,
+    back_query: Query<(), With<HighScoresBack>>,
+    mut next_menu: ResMut<NextState<MenuState>>,
+) {
+    let back_pressed = pressed_events
+        .read()
+        .any(|event| back_query.contains(event.0));
+
+    if back_pressed || action_input.just_pressed(Action::Dismiss) {
+        next_menu.set(MenuState::Main);
+    }
+}
@@ -0,0 +1,406 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use crate::{
+    actions::{Action, ActionInput},
+    audio::AudioVolume,
+    game_states::GameState,
+    settings::{Settings, set_fullscreen},
+    themes::{CurrentThemeIndex, Theme, ThemeHandle, ThemeManifest},
+};
+
+const VOLUME_STEP: f32 = 0.1;
+
+/// Menu that is currently open, if any.
+#[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MenuState {
+    #[default]
+    Closed,
+    Main,
+    GameModes,
+    Characters,
+    Themes,
+    HighScores,
+    Pause,
+    Settings,
+    Controls,
+}
+
+#[derive(Component)]
+pub struct MenuRoot;
+
+// buttons are selected with the arrow keys in the order of their index
+#[derive(Component)]
+pub struct MenuButton(pub usize);
+
+#[derive(Resource, Default)]
+pub struct MenuSelection(pub usize);
+
+/// Systems that turn keyboard and mouse input into menu events.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MenuNavigation;
+
+// sent when a button is clicked or confirmed with Enter or Space
+#[derive(Event)]
+pub struct MenuPressed(pub Entity);
+
+// sent when the left or right arrow is pressed on a button
+#[derive(Event)]
+pub struct MenuAdjusted {
+    pub button: Entity,
+    pub step: i32,
+}
+
+#[derive(Component, Clone, Copy)]
+pub enum SettingsAction {
+    Volume,
+    Fullscreen,
+    Theme,
+    Controls,
+    Back,
+}
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<MenuState>()
+            .init_resource::<MenuSelection>()
+            .add_event::<MenuPressed>()
+            .add_event::<MenuAdjusted>()
+            .add_systems(
+                Update,
+                (navigate_menu.in_set(MenuNavigation), highlight_menu_buttons)
+                    .chain()
+                    .run_if(not(in_state(MenuState::Closed))),
+            )
+            .add_systems(OnEnter(MenuState::Settings), spawn_settings_menu)
+            .add_systems(OnExit(MenuState::Settings), despawn_menu)
+            .add_systems(
+                Update,
+                (handle_settings_menu, update_settings_labels)
+                    .chain()
+                    .after(navigate_menu)
+                    .run_if(in_state(MenuState::Settings)),
+            );
+    }
+}
+
+/// Spawns a centered column with a title and one button per item.
+pub fn spawn_menu<A: Component + Clone>(
+    commands: &mut Commands,
+    title: &str,
+    items: &[(String, A)],
+    text_color: Color,
+) {
+    let root = spawn_menu_root(commands, title, text_color);
+
+    commands.entity(root).with_children(|parent| {
+        for (index, (label, action)) in items.iter().enumerate() {
+            parent.spawn(menu_button(label, index, action.clone(), text_color));
+        }
+    });
+}
+
+/// Spawns the centered column of a menu with its title. Content is added as children.
+pub fn spawn_menu_root(commands: &mut Commands, title: &str, text_color: Color) -> Entity {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(8.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+            MenuRoot,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(title),
+                TextFont {
+                    font_size: 40.0,
+                    ..default()
+                },
+                TextColor(text_color),
+                Node {
+                    margin: UiRect::bottom(Val::Px(16.0)),
+                    ..default()
+                },
+            ));
+        })
+        .id()
+}
+
+pub fn menu_button<A: Component>(
+    label: &str,
+    index: usize,
+    action: A,
+    text_color: Color,
+) -> impl Bundle + use<A> {
+    (
+        Button,
+        Text::new(label),
+        TextFont {
+            font_size: 28.0,
+            ..default()
+        },
+        TextColor(text_color),
+        Node {
+            padding: UiRect::axes(Val::Px(16.0), Val::Px(4.0)),
+            ..default()
+        },
+        MenuButton(index),
+        action,
+    )
+}
+
+pub fn despawn_menu(
+    mut commands: Commands,
+    query: Query<Entity, With<MenuRoot>>,
+    mut selection: ResMut<MenuSelection>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+    selection.0 = 0;
+}
+
+pub fn navigate_menu(
+    kb_input: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    action_input: Res<ActionInput>,
+    mut selection: ResMut<MenuSelection>,
+    button_query: Query<(Entity, &MenuButton, &Interaction), Changed<Interaction>>,
+    all_buttons: Query<(Entity, &MenuButton)>,
+    mut pressed_events: EventWriter<MenuPressed>,
+    mut adjusted_events: EventWriter<MenuAdjusted>,
+) {
+    let count = all_buttons.iter().count();
+    if count == 0 {
+        return;
+    }
+
+    // mouse
+    for (entity, button, interaction) in button_query.iter() {
+        match interaction {
+            Interaction::Hovered => selection.0 = button.0,
+            Interaction::Pressed => {
+                selection.0 = button.0;
+                pressed_events.write(MenuPressed(entity));
+            }
+            Interaction::None => {}
+        }
+    }
+
+    // keyboard and d-pad
+    let just_pressed = |key: KeyCode, button: GamepadButton| {
+        kb_input.just_pressed(key) || gamepads.iter().any(|gamepad| gamepad.just_pressed(button))
+    };
+
+    if just_pressed(KeyCode::ArrowDown, GamepadButton::DPadDown) {
+        selection.0 = (selection.0 + 1) % count;
+    } else if just_pressed(KeyCode::ArrowUp, GamepadButton::DPadUp) {
+        selection.0 = (selection.0 + count - 1) % count;
+    }
+
+    let Some((selected, _)) = all_buttons
+        .iter()
+        .find(|(_, button)| button.0 == selection.0)
+    else {
+        return;
+    };
+
+    // enter always works, even if confirm is bound to another key
+    if action_input.just_pressed(Action::Confirm)
+        || kb_input.any_just_pressed([KeyCode::Enter, KeyCode::NumpadEnter])
+    {
+        pressed_events.write(MenuPressed(selected));
+    } else if just_pressed(KeyCode::ArrowLeft, GamepadButton::DPadLeft) {
+        adjusted_events.write(MenuAdjusted {
+            button: selected,
+            step: -1,
+        });
+    } else if just_pressed(KeyCode::ArrowRight, GamepadButton::DPadRight) {
+        adjusted_events.write(MenuAdjusted {
+            button: selected,
+            step: 1,
+        });
+    }
+}
+
+pub fn highlight_menu_buttons(
+    selection: Res<MenuSelection>,
+    mut button_query: Query<(&MenuButton, Option<&mut TextColor>, &mut BackgroundColor)>,
+    theme_handle: Res<ThemeHandle>,
+    themes: Res<Assets<Theme>>,
+) {
+    let mut text_color = Color::srgb(1.0, 1.0, 1.0);
+    let mut highlight_color = Color::srgb(1.0, 1.0, 0.2);
+
+    if let Some(theme) = themes.get(&theme_handle.0) {
+        text_color = theme.text_color.to_color();
+        highlight_color = theme.player_broken_color.to_color();
+    }
+
+    // buttons without their own text only get the background highlight
+    for (button, color, mut background) in button_query.iter_mut() {
+        let selected = button.0 == selection.0;
+
+        if let Some(mut color) = color {
+            color.0 = if selected {
+                highlight_color
+            } else {
+                text_color
+            };
+        }
+        background.0 = if selected {
+            text_color.with_alpha(0.15)
+        } else {
+            Color::NONE
+        };
+    }
+}
+
+pub fn spawn_settings_menu(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    volume: Option<Res<AudioVolume>>,
+    current_index: Res<CurrentThemeIndex>,
+    theme_handle: Res<ThemeHandle>,
+    themes: Res<Assets<Theme>>,
+) {
+    let mut text_color = Color::srgb(1.0, 1.0, 1.0);
+
+    if let Some(theme) = themes.get(&theme_handle.0) {
+        text_color = theme.text_color.to_color();
+    }
+
+    let volume = volume.map_or(settings.volume, |volume| volume.0);
+    let items = [
+        SettingsAction::Volume,
+        SettingsAction::Fullscreen,
+        SettingsAction::Theme,
+        SettingsAction::Controls,
+        SettingsAction::Back,
+    ]
+    .map(|action| {
+        (
+            settings_label(action, &settings, volume, current_index.0),
+            action,
+        )
+    });
+
+    spawn_menu(&mut commands, "Settings", &items, text_color);
+}
+
+fn settings_label(
+    action: SettingsAction,
+    settings: &Settings,
+    volume: f32,
+    theme_index: usize,
+) -> String {
+    match action {
+        SettingsAction::Volume => format!("< Volume: {:.0}% >", volume * 100.0),
+        SettingsAction::Fullscreen => format!(
+            "Fullscreen: {}",
+            if settings.fullscreen { "On" } else { "Off" }
+        ),
+        SettingsAction::Theme => format!(
+            "< Theme: {} >",
+            ThemeManifest::builtin()
+                .themes
+                .get(theme_index)
+                .map_or("Default", |theme| theme.name.as_str())
+        ),
+        SettingsAction::Controls => "Controls".to_string(),
+        SettingsAction::Back => "Back".to_string(),
+    }
+}
+
+pub fn handle_settings_menu(
+    action_input: Res<ActionInput>,
+    mut pressed_events: EventReader<MenuPressed>,
+    mut adjusted_events: EventReader<MenuAdjusted>,
+    action_query: Query<&SettingsAction>,
+    mut settings: ResMut<Settings>,
+    mut volume: Option<ResMut<AudioVolume>>,
+    mut current_index: ResMut<CurrentThemeIndex>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+    game_state: Res<State<GameState>>,
+    mut next_menu: ResMut<NextState<MenuState>>,
+) {
+    // step is none when the button was pressed instead of adjusted
+    let mut changes: Vec<(SettingsAction, Option<i32>)> = pressed_events
+        .read()
+        .filter_map(|event| action_query.get(event.0).ok().map(|action| (*action, None)))
+        .collect();
+    changes.extend(adjusted_events.read().filter_map(|event| {
+        action_query
+            .get(event.button)
+            .ok()
+            .map(|action| (*action, Some(event.step)))
+    }));
+
+    if action_input.just_pressed(Action::Dismiss) {
+        changes.push((SettingsAction::Back, None));
+    }
+
+    let theme_count = ThemeManifest::builtin().themes.len();
+
+    for (action, step) in changes {
+        match (action, step) {
+            (SettingsAction::Volume, step) => {
+                if let Some(volume) = volume.as_mut() {
+                    // wraps around when pressed at full volume
+                    volume.0 = if step.is_none() && volume.0 >= 1.0 {
+                        0.0
+                    } else {
+                        let steps = (volume.0 / VOLUME_STEP).round() + step.unwrap_or(1) as f32;
+                        (steps * VOLUME_STEP).clamp(0.0, 1.0)
+                    };
+                }
+            }
+            (SettingsAction::Fullscreen, _) => {
+                let fullscreen = !settings.fullscreen;
+                set_fullscreen(fullscreen, &mut settings, &mut window_query);
+            }
+            (SettingsAction::Theme, step) => {
+                if theme_count > 0 {
+                    current_index.0 = (current_index.0 as i32 + step.unwrap_or(1))
+                        .rem_euclid(theme_count as i32)
+                        as usize;
+                }
+            }
+            (SettingsAction::Controls, None) => next_menu.set(MenuState::Controls),
+            (SettingsAction::Back, None) => {
+                next_menu.set(if *game_state.get() == GameState::Paused {
+                    MenuState::Pause
+                } else {
+                    MenuState::Main
+                });
+            }
+            (SettingsAction::Controls | SettingsAction::Back, Some(_)) => {}
+        }
+    }
+}
+
+pub fn update_settings_labels(
+    settings: Res<Settings>,
+    volume: Option<Res<AudioVolume>>,
+    current_index: Res<CurrentThemeIndex>,
+    mut button_query: Query<(&SettingsAction, &mut Text)>,
+) {
+    let volume = volume.map_or(settings.volume, |volume| volume.0);
+
+    for (action, mut text) in button_query.iter_mut() {
+        let label = settings_label(*action, &settings, volume, current_index.0);
+        if text.0 != label {
+            text.0 = label;
+        }
+    }
+}
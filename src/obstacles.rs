@@ -0,0 +1,784 @@
+use bevy::asset::Asset;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::reflect::Reflect;
+use bevy_common_assets::ron::RonAssetPlugin;
+use bevy_rapier2d::prelude::*;
+use std::f32::consts::TAU;
+
+use rand::{Rng, distributions::WeightedIndex, prelude::Distribution, rngs::StdRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{hazards::Hazard, pickups::PickupKind, themes::Theme};
+
+const MIN_MOTION_PERIOD: f32 = 0.1;
+
+#[derive(Resource, Default)]
+pub struct ObstacleCatalogHandle(pub Handle<ObstacleCatalog>);
+
+// used until the catalog asset is loaded, so the first obstacles do not depend on loading times
+#[derive(Resource)]
+pub struct BuiltinObstacleCatalog(pub ObstacleCatalog);
+
+/// The loaded catalog, or the built-in one while it is loading.
+#[derive(SystemParam)]
+pub struct ObstacleCatalogs<'w> {
+    handle: Res<'w, ObstacleCatalogHandle>,
+    catalogs: Res<'w, Assets<ObstacleCatalog>>,
+    builtin: Res<'w, BuiltinObstacleCatalog>,
+}
+
+pub struct ObstacleCatalogPlugin;
+
+impl Plugin for ObstacleCatalogPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(RonAssetPlugin::<ObstacleCatalog>::new(&["obstacles.ron"]))
+            .insert_resource(BuiltinObstacleCatalog(ObstacleCatalog::builtin()))
+            .add_systems(PreStartup, load_obstacle_catalog);
+    }
+}
+
+/// Every kind of obstacle that can be spawned.
+#[derive(Asset, Serialize, Deserialize, Clone, Debug, Reflect)]
+pub struct ObstacleCatalog {
+    pub obstacles: Vec<ObstacleKind>,
+    // how spawning changes as the score grows, sorted by score. values between steps are blended.
+    #[serde(default = "default_difficulty")]
+    pub difficulty: Vec<DifficultyStep>,
+    // paths of hand-made segments in the assets folder
+    #[serde(default)]
+    pub segments: Vec<String>,
+    // pickups that spawn between the obstacles
+    #[serde(default)]
+    pub pickups: Vec<PickupSpawn>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Reflect)]
+pub struct PickupSpawn {
+    pub kind: PickupKind,
+    // chance to be picked relative to the other pickups
+    #[serde(default = "default_one")]
+    pub weight: f32,
+    // only spawns once the score reaches this
+    #[serde(default)]
+    pub min_score: i32,
+}
+
+/// Spawn settings at a certain score.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Reflect)]
+pub struct DifficultyStep {
+    pub score: i32,
+    // minimum seconds between two obstacles
+    pub spawn_delay: f32,
+    // chance per tick to spawn an obstacle once the delay is over
+    pub spawn_chance: f64,
+    // minimum vertical distance between two obstacles
+    pub min_distance: f32,
+    // multiplies the lower and upper end of every size range
+    #[serde(default = "default_size_scale")]
+    pub size_scale: (f32, f32),
+    // chance that a spawn is a segment instead of a single obstacle
+    #[serde(default)]
+    pub segment_chance: f64,
+    // chance per tick to spawn a pickup once the pickup delay is over
+    #[serde(default)]
+    pub pickup_chance: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Reflect)]
+pub struct ObstacleKind {
+    // entities are named "obstacle_" followed by this
+    pub name: String,
+    pub shape: ObstacleShape,
+    // range the width is picked from. circles use it as the diameter.
+    #[serde(default = "default_size")]
+    pub width: (f32, f32),
+    // range the height is picked from. unused by circles.
+    #[serde(default = "default_size")]
+    pub height: (f32, f32),
+    // chance to be picked relative to the other kinds
+    #[serde(default = "default_one")]
+    pub weight: f32,
+    #[serde(default)]
+    pub body: ObstacleBody,
+    #[serde(default = "default_gravity_scale")]
+    pub gravity_scale: f32,
+    #[serde(default = "default_friction")]
+    pub friction: f32,
+    #[serde(default)]
+    pub restitution: f32,
+    #[serde(default = "default_one")]
+    pub density: f32,
+    // range of the initial angular velocity in radians per second
+    #[serde(default)]
+    pub spin: (f32, f32),
+    // spawns at a random angle if true
+    #[serde(default = "default_true")]
+    pub random_rotation: bool,
+    // only spawns once the score reaches this
+    #[serde(default)]
+    pub min_score: i32,
+    // moves on a fixed path instead of falling, the body is always kinematic then
+    #[serde(default)]
+    pub motion: Option<ObstacleMotion>,
+    // special effect on contact with the player
+    #[serde(default)]
+    pub hazard: Option<Hazard>,
+}
+
+/// Path of a kinematic obstacle. Ranges are picked from for every obstacle.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Reflect)]
+pub enum ObstacleMotion {
+    // sweeps left and right between the walls, speed in pixels per second
+    Slider {
+        speed: (f32, f32),
+    },
+    // rotates around the point it spawned at, speed in radians per second
+    Spinner {
+        speed: (f32, f32),
+    },
+    // hangs from a wall on a rod of this length and swings up to amplitude radians into the shaft
+    Pendulum {
+        length: (f32, f32),
+        amplitude: f32,
+        period: (f32, f32),
+    },
+    // slides out of a wall by up to reach pixels and back
+    Piston {
+        reach: (f32, f32),
+        period: (f32, f32),
+    },
+}
+
+/// Moves a kinematic obstacle along its path in the fixed update.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct MovingObstacle {
+    pub path: MotionPath,
+    // seconds the obstacle has been moving
+    pub age: f32,
+}
+
+/// An `ObstacleMotion` with all random values picked and placed in the world.
+#[derive(Clone, Copy, Debug)]
+pub enum MotionPath {
+    Slider {
+        min_x: f32,
+        max_x: f32,
+        y: f32,
+        // distance along the path at age 0
+        start: f32,
+        speed: f32,
+        angle: f32,
+    },
+    Spinner {
+        center: Vec2,
+        start_angle: f32,
+        speed: f32,
+    },
+    Pendulum {
+        pivot: Vec2,
+        length: f32,
+        amplitude: f32,
+        period: f32,
+        // 1.0 swings to the right, -1.0 to the left
+        direction: f32,
+    },
+    Piston {
+        retracted: Vec2,
+        reach: f32,
+        period: f32,
+        // 1.0 extends to the right, -1.0 to the left
+        direction: f32,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, Reflect)]
+pub enum ObstacleShape {
+    Rectangle,
+    Circle,
+    Triangle,
+    // rounded on top and bottom, the width is the diameter
+    Capsule,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+pub enum ObstacleBody {
+    // falls and gets pushed around
+    #[default]
+    Dynamic,
+    // moves at its own velocity and can not be pushed
+    Kinematic,
+    // never moves
+    Fixed,
+}
+
+/// Random rolls that decide how an obstacle looks.
+/// Kept on the obstacle so it can be redrawn the same way when the theme changes.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ObstacleLook {
+    pub shape: ObstacleShape,
+    // hazards use the color the theme has for them
+    pub hazard: Option<Hazard>,
+    // -1.0 to 1.0 per channel, scaled by the color variation of the theme
+    pub color_offset: Vec3,
+    // 0.0 to 1.0, picks one of the textures the theme has for the shape
+    pub texture_roll: f32,
+}
+
+impl ObstacleCatalog {
+    /// Catalog compiled into the binary, available before any asset is loaded.
+    pub fn builtin() -> Self {
+        ron::from_str(include_str!("../assets/obstacles/default.obstacles.ron"))
+            .expect("built-in obstacle catalog is valid")
+    }
+
+    /// Picks a kind that is unlocked at the score by its weight, scaled by `weight_scale`.
+    /// Returns `None` if no unlocked kind has a positive weight.
+    pub fn pick(
+        &self,
+        rng: &mut StdRng,
+        score: i32,
+        weight_scale: impl Fn(&ObstacleKind) -> f32,
+    ) -> Option<&ObstacleKind> {
+        let weights = self.obstacles.iter().map(|kind| {
+            if score >= kind.min_score {
+                kind.weight * weight_scale(kind)
+            } else {
+                0.0
+            }
+        });
+        let index = WeightedIndex::new(weights).ok()?;
+        self.obstacles.get(index.sample(rng))
+    }
+
+    /// Picks a pickup that is unlocked at the score by its weight.
+    /// Returns `None` if no unlocked pickup has a positive weight.
+    pub fn pick_pickup(&self, rng: &mut StdRng, score: i32) -> Option<PickupKind> {
+        let weights = self.pickups.iter().map(|pickup| {
+            if score >= pickup.min_score {
+                pickup.weight
+            } else {
+                0.0
+            }
+        });
+        let index = WeightedIndex::new(weights).ok()?;
+        self.pickups
+            .get(index.sample(rng))
+            .map(|pickup| pickup.kind)
+    }
+
+    pub fn kind(&self, name: &str) -> Option<&ObstacleKind> {
+        self.obstacles.iter().find(|kind| kind.name == name)
+    }
+
+    /// Spawn settings at the score, blended between the two closest steps.
+    pub fn difficulty_at(&self, score: i32) -> DifficultyStep {
+        let (Some(first), Some(last)) = (self.difficulty.first(), self.difficulty.last()) else {
+            return DifficultyStep::default();
+        };
+        if score <= first.score {
+            return *first;
+        }
+
+        for pair in self.difficulty.windows(2) {
+            let (from, to) = (pair[0], pair[1]);
+            if score < to.score {
+                let t = (score - from.score) as f32 / (to.score - from.score) as f32;
+                return from.lerp(&to, t);
+            }
+        }
+        *last
+    }
+}
+
+impl Default for DifficultyStep {
+    fn default() -> Self {
+        Self {
+            score: 0,
+            spawn_delay: 0.5,
+            spawn_chance: 0.05,
+            min_distance: 700.0,
+            size_scale: (1.0, 1.0),
+            segment_chance: 0.0,
+            pickup_chance: 0.0,
+        }
+    }
+}
+
+impl DifficultyStep {
+    fn lerp(&self, other: &DifficultyStep, t: f32) -> DifficultyStep {
+        let mix = |a: f32, b: f32| a + (b - a) * t;
+        DifficultyStep {
+            score: mix(self.score as f32, other.score as f32) as i32,
+            spawn_delay: mix(self.spawn_delay, other.spawn_delay),
+            spawn_chance: self.spawn_chance + (other.spawn_chance - self.spawn_chance) * t as f64,
+            min_distance: mix(self.min_distance, other.min_distance),
+            size_scale: (
+                mix(self.size_scale.0, other.size_scale.0),
+                mix(self.size_scale.1, other.size_scale.1),
+            ),
+            segment_chance: self.segment_chance
+                + (other.segment_chance - self.segment_chance) * t as f64,
+            pickup_chance: self.pickup_chance
+                + (other.pickup_chance - self.pickup_chance) * t as f64,
+        }
+    }
+}
+
+impl ObstacleCatalogs<'_> {
+    pub fn get(&self) -> &ObstacleCatalog {
+        self.catalogs.get(&self.handle.0).unwrap_or(&self.builtin.0)
+    }
+}
+
+impl ObstacleKind {
+    pub fn pick_size(&self, rng: &mut StdRng, (low, high): (f32, f32)) -> Vec2 {
+        Vec2::new(
+            pick_in_range(rng, (self.width.0 * low, self.width.1 * high)),
+            pick_in_range(rng, (self.height.0 * low, self.height.1 * high)),
+        )
+    }
+
+    pub fn pick_spin(&self, rng: &mut StdRng) -> f32 {
+        pick_in_range(rng, self.spin)
+    }
+}
+
+impl ObstacleLook {
+    /// Always rolls the same amount of numbers, so the theme does not change the obstacles of a seed.
+    pub fn roll(shape: ObstacleShape, hazard: Option<Hazard>, rng: &mut StdRng) -> Self {
+        Self {
+            shape,
+            hazard,
+            color_offset: Vec3::new(
+                rng.gen_range(-1.0..=1.0),
+                rng.gen_range(-1.0..=1.0),
+                rng.gen_range(-1.0..=1.0),
+            ),
+            texture_roll: rng.gen_range(0.0..1.0),
+        }
+    }
+
+    pub fn material(&self, theme: Option<&Theme>, asset_server: &AssetServer) -> ColorMaterial {
+        if let Some(hazard) = self.hazard {
+            return ColorMaterial::from(hazard_color(hazard, theme));
+        }
+
+        let mut grayscale = true;
+        let mut color_variation = 0.1;
+        let mut base_color = Vec3::new(0.3, 0.3, 0.3); // x = r, y = g, z = b
+        let mut texture = None;
+        let mut tint = false;
+
+        if let Some(theme) = theme {
+            grayscale = theme.obstacles_grayscale;
+            color_variation = theme.obstacles_color_variation as f32;
+            base_color = theme.obstacles_base_color.to_vec();
+            tint = theme.obstacles_texture_tint;
+            texture = theme
+                .obstacle_textures
+                .get(&self.shape)
+                .filter(|textures| !textures.is_empty())
+                .map(|textures| {
+                    let index = (self.texture_roll * textures.len() as f32) as usize;
+                    textures[index.min(textures.len() - 1)].clone()
+                });
+        }
+
+        let Some(texture) = texture else {
+            let color =
+                (base_color + self.color_offset * color_variation).clamp(Vec3::ZERO, Vec3::ONE);
+            return ColorMaterial::from(to_color(color, grayscale));
+        };
+
+        // the tint only darkens, so the texture keeps its own colors
+        let color = if tint {
+            to_color(
+                Vec3::ONE - self.color_offset.abs() * color_variation,
+                grayscale,
+            )
+        } else {
+            Color::WHITE
+        };
+
+        ColorMaterial {
+            color,
+            texture: Some(asset_server.load(format!("themes/{}", texture))),
+            ..default()
+        }
+    }
+}
+
+impl ObstacleMotion {
+    /// Picks the random values of the path for an obstacle of the size spawned at the position.
+    /// `x_range` is the free space between the walls.
+    pub fn resolve(
+        &self,
+        rng: &mut StdRng,
+        size: Vec2,
+        position: Vec2,
+        angle: f32,
+        x_range: (f32, f32),
+    ) -> MotionPath {
+        // -1.0 is the left wall, 1.0 the right one
+        let wall = if rng.gen_bool(0.5) { -1.0 } else { 1.0 };
+        let wall_x = if wall < 0.0 { x_range.0 } else { x_range.1 };
+
+        match *self {
+            ObstacleMotion::Slider { speed } => {
+                let min_x = x_range.0 + size.x / 2.0;
+                let max_x = (x_range.1 - size.x / 2.0).max(min_x);
+                MotionPath::Slider {
+                    min_x,
+                    max_x,
+                    y: position.y,
+                    start: (position.x - min_x).clamp(0.0, max_x - min_x),
+                    speed: pick_in_range(rng, speed) * wall,
+                    angle,
+                }
+            }
+            ObstacleMotion::Spinner { speed } => MotionPath::Spinner {
+                center: position,
+                start_angle: angle,
+                speed: pick_in_range(rng, speed),
+            },
+            ObstacleMotion::Pendulum {
+                length,
+                amplitude,
+                period,
+            } => {
+                let length = pick_in_range(rng, length);
+                MotionPath::Pendulum {
+                    pivot: Vec2::new(wall_x, position.y + length),
+                    length,
+                    amplitude,
+                    period: pick_in_range(rng, period).max(MIN_MOTION_PERIOD),
+                    direction: -wall,
+                }
+            }
+            ObstacleMotion::Piston { reach, period } => MotionPath::Piston {
+                // hidden in the wall when retracted
+                retracted: Vec2::new(wall_x + wall * size.x / 2.0, position.y),
+                reach: pick_in_range(rng, reach),
+                period: pick_in_range(rng, period).max(MIN_MOTION_PERIOD),
+                direction: -wall,
+            },
+        }
+    }
+}
+
+impl MotionPath {
+    /// Position and rotation after moving for `age` seconds.
+    pub fn transform_at(&self, age: f32) -> (Vec2, f32) {
+        match *self {
+            MotionPath::Slider {
+                min_x,
+                max_x,
+                y,
+                start,
+                speed,
+                angle,
+            } => {
+                // bounces between both ends
+                let span = max_x - min_x;
+                if span <= 0.0 {
+                    return (Vec2::new(min_x, y), angle);
+                }
+                let distance = (start + speed * age).rem_euclid(2.0 * span);
+                let x = min_x + span - (distance - span).abs();
+                (Vec2::new(x, y), angle)
+            }
+            MotionPath::Spinner {
+                center,
+                start_angle,
+                speed,
+            } => (center, start_angle + speed * age),
+            MotionPath::Pendulum {
+                pivot,
+                length,
+                amplitude,
+                period,
+                direction,
+            } => {
+                // starts hanging straight down along the wall
+                let swing = amplitude * ease_in_out(age / period) * direction;
+                let offset = Vec2::new(length * swing.sin(), -length * swing.cos());
+                (pivot + offset, swing)
+            }
+            MotionPath::Piston {
+                retracted,
+                reach,
+                period,
+                direction,
+            } => {
+                let extension = reach * ease_in_out(age / period);
+                (retracted + Vec2::X * extension * direction, 0.0)
+            }
+        }
+    }
+
+    /// Moves the path up by `dy`, used when the world is recentered.
+    pub fn shift(&mut self, dy: f32) {
+        match self {
+            MotionPath::Slider { y, .. } => *y += dy,
+            MotionPath::Spinner { center, .. } => center.y += dy,
+            MotionPath::Pendulum { pivot, .. } => pivot.y += dy,
+            MotionPath::Piston { retracted, .. } => retracted.y += dy,
+        }
+    }
+
+    /// Length of the rod from the obstacle to its pivot, if it hangs from one.
+    pub fn rod_length(&self) -> Option<f32> {
+        match *self {
+            MotionPath::Pendulum { length, .. } => Some(length),
+            _ => None,
+        }
+    }
+}
+
+impl ObstacleShape {
+    // every mesh is centered on its bounding box, so textures cover the whole shape
+
+    pub fn mesh(&self, size: Vec2) -> Mesh {
+        match self {
+            ObstacleShape::Rectangle => Rectangle::new(size.x, size.y).into(),
+            ObstacleShape::Circle => Circle::new(size.x / 2.0).into(),
+            ObstacleShape::Triangle => {
+                let (point_a, point_b, point_c) = triangle_points(size);
+                Triangle2d::new(point_a, point_b, point_c).into()
+            }
+            ObstacleShape::Capsule => {
+                Capsule2d::new(size.x / 2.0, (size.y - size.x).max(0.0)).into()
+            }
+        }
+    }
+
+    pub fn collider(&self, size: Vec2) -> Collider {
+        match self {
+            ObstacleShape::Rectangle => Collider::cuboid(size.x / 2.0, size.y / 2.0),
+            ObstacleShape::Circle => Collider::ball(size.x / 2.0),
+            ObstacleShape::Triangle => {
+                let (point_a, point_b, point_c) = triangle_points(size);
+                Collider::triangle(point_a, point_b, point_c)
+            }
+            ObstacleShape::Capsule => {
+                Collider::capsule_y(((size.y - size.x) / 2.0).max(0.0), size.x / 2.0)
+            }
+        }
+    }
+}
+
+impl ObstacleBody {
+    pub fn rigid_body(&self) -> RigidBody {
+        match self {
+            ObstacleBody::Dynamic => RigidBody::Dynamic,
+            ObstacleBody::Kinematic => RigidBody::KinematicVelocityBased,
+            ObstacleBody::Fixed => RigidBody::Fixed,
+        }
+    }
+}
+
+pub fn load_obstacle_catalog(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let catalog_handle =
+        ObstacleCatalogHandle(asset_server.load("obstacles/default.obstacles.ron"));
+    commands.insert_resource(catalog_handle);
+}
+
+// goes from 0.0 to 1.0 and back once per cycle, slowing down at both ends
+fn ease_in_out(cycles: f32) -> f32 {
+    0.5 - 0.5 * (cycles * TAU).cos()
+}
+
+fn hazard_color(hazard: Hazard, theme: Option<&Theme>) -> Color {
+    let Some(theme) = theme else {
+        return match hazard {
+            Hazard::Spikes => Color::srgb(0.75, 0.2, 0.2),
+            Hazard::Bouncy { .. } => Color::srgb(0.2, 0.8, 0.4),
+            Hazard::Sticky { .. } => Color::srgb(0.8, 0.6, 0.1),
+            Hazard::SlowZone { .. } => Color::srgba(0.3, 0.5, 1.0, 0.3),
+        };
+    };
+
+    match hazard {
+        Hazard::Spikes => theme.spikes_color.to_color(),
+        Hazard::Bouncy { .. } => theme.bouncy_color.to_color(),
+        Hazard::Sticky { .. } => theme.sticky_color.to_color(),
+        Hazard::SlowZone { .. } => theme.slow_zone_color.to_color(),
+    }
+}
+
+fn to_color(color: Vec3, grayscale: bool) -> Color {
+    if grayscale {
+        Color::srgb(color.x, color.x, color.x)
+    } else {
+        Color::srgb(color.x, color.y, color.z)
+    }
+}
+
+// pointing up, centered on the bounding box
+fn triangle_points(size: Vec2) -> (Vec2, Vec2, Vec2) {
+    (
+        Vec2::new(0.0, size.y / 2.0),
+        Vec2::new(-(size.x / 2.0), -(size.y / 2.0)),
+        Vec2::new(size.x / 2.0, -(size.y / 2.0)),
+    )
+}
+
+// also accepts a fixed value like (150.0, 150.0) and reversed bounds
+fn pick_in_range(rng: &mut StdRng, (a, b): (f32, f32)) -> f32 {
+    rng.gen_range(a.min(b)..=a.max(b))
+}
+
+fn default_size() -> (f32, f32) {
+    (120.0, 180.0)
+}
+
+fn default_size_scale() -> (f32, f32) {
+    (1.0, 1.0)
+}
+
+fn default_difficulty() -> Vec<DifficultyStep> {
+    vec![DifficultyStep::default()]
+}
+
+fn default_one() -> f32 {
+    1.0
+}
+
+fn default_gravity_scale() -> f32 {
+    0.1
+}
+
+fn default_friction() -> f32 {
+    0.5
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn catalog(steps: &[(i32, f32)]) -> ObstacleCatalog {
+        ObstacleCatalog {
+            difficulty: steps
+                .iter()
+                .map(|(score, spawn_delay)| DifficultyStep {
+                    score: *score,
+                    spawn_delay: *spawn_delay,
+                    ..default()
+                })
+                .collect(),
+            ..ObstacleCatalog::builtin()
+        }
+    }
+
+    fn slider(start: f32, speed: f32) -> MotionPath {
+        MotionPath::Slider {
+            min_x: -100.0,
+            max_x: 100.0,
+            y: 50.0,
+            start,
+            speed,
+            angle: 0.0,
+        }
+    }
+
+    #[test]
+    fn difficulty_is_clamped_below_the_first_step() {
+        let catalog = catalog(&[(1000, 1.0), (3000, 0.2)]);
+        assert_eq!(catalog.difficulty_at(1000), catalog.difficulty[0]);
+        assert_eq!(catalog.difficulty_at(0), catalog.difficulty[0]);
+        assert_eq!(catalog.difficulty_at(-500), catalog.difficulty[0]);
+    }
+
+    #[test]
+    fn difficulty_is_clamped_above_the_last_step() {
+        let catalog = catalog(&[(1000, 1.0), (3000, 0.2)]);
+        assert_eq!(catalog.difficulty_at(3000), catalog.difficulty[1]);
+        assert_eq!(catalog.difficulty_at(100_000), catalog.difficulty[1]);
+    }
+
+    #[test]
+    fn difficulty_blends_between_steps() {
+        let catalog = catalog(&[(0, 1.0), (1000, 0.5), (3000, 0.1)]);
+        let step = catalog.difficulty_at(500);
+        assert_eq!(step.score, 500);
+        assert!((step.spawn_delay - 0.75).abs() < 1e-6);
+
+        // uses the pair of steps the score is between
+        let step = catalog.difficulty_at(2000);
+        assert_eq!(step.score, 2000);
+        assert!((step.spawn_delay - 0.3).abs() < 1e-6);
+    }
+
+    #[test]
+    fn difficulty_without_steps_is_the_default() {
+        assert_eq!(catalog(&[]).difficulty_at(1000), DifficultyStep::default());
+    }
+
+    #[test]
+    fn lerp_hits_both_ends() {
+        let from = DifficultyStep {
+            spawn_chance: 0.25,
+            size_scale: (1.0, 1.0),
+            ..default()
+        };
+        let to = DifficultyStep {
+            score: 1000,
+            spawn_chance: 0.75,
+            size_scale: (0.5, 2.0),
+            ..default()
+        };
+        assert_eq!(from.lerp(&to, 0.0), from);
+        assert_eq!(from.lerp(&to, 1.0), to);
+
+        let half = from.lerp(&to, 0.5);
+        assert_eq!(half.spawn_chance, 0.5);
+        assert_eq!(half.size_scale, (0.75, 1.5));
+    }
+
+    #[test]
+    fn slider_bounces_between_both_ends() {
+        // 200 pixels between the ends at 100 pixels per second
+        let path = slider(0.0, 100.0);
+        assert_eq!(path.transform_at(0.0).0, Vec2::new(-100.0, 50.0));
+        assert_eq!(path.transform_at(1.0).0, Vec2::new(0.0, 50.0));
+        assert_eq!(path.transform_at(2.0).0, Vec2::new(100.0, 50.0));
+        assert_eq!(path.transform_at(3.0).0, Vec2::new(0.0, 50.0));
+        assert_eq!(path.transform_at(4.0).0, Vec2::new(-100.0, 50.0));
+    }
+
+    #[test]
+    fn slider_moving_left_bounces_off_the_left_end() {
+        let path = slider(100.0, -100.0);
+        assert_eq!(path.transform_at(0.0).0.x, 0.0);
+        assert_eq!(path.transform_at(1.0).0.x, -100.0);
+        assert_eq!(path.transform_at(2.0).0.x, 0.0);
+        assert_eq!(path.transform_at(3.0).0.x, 100.0);
+    }
+
+    #[test]
+    fn slider_stays_within_its_ends() {
+        let path = slider(37.0, 130.0);
+        for tick in 0..600 {
+            let x = path.transform_at(tick as f32 / 60.0).0.x;
+            assert!((-100.0..=100.0).contains(&x), "{x} is outside the slider");
+        }
+    }
+
+    #[test]
+    fn slider_without_room_stays_at_its_end() {
+        let path = MotionPath::Slider {
+            min_x: 20.0,
+            max_x: 20.0,
+            y: 0.0,
+            start: 0.0,
+            speed: 100.0,
+            angle: 0.5,
+        };
+        assert_eq!(path.transform_at(3.0), (Vec2::new(20.0, 0.0), 0.5));
+    }
+}
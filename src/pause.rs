@@ -0,0 +1,213 @@
+use bevy::input::gamepad::{GamepadConnection, GamepadConnectionEvent};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    actions::{Action, ActionInput},
+    game_states::{GameState, NewRun},
+    jumpscare::Jumpscare,
+    menu::{MenuPressed, MenuState, despawn_menu, navigate_menu, spawn_menu},
+    replay::ReplayPlayback,
+    skeleton::skeleton_loaded,
+    themes::{Theme, ThemeHandle},
+};
+
+#[derive(Component, Clone, Copy)]
+pub enum PauseAction {
+    Resume,
+    Restart,
+    Settings,
+    Quit,
+}
+
+// on-screen pause button for mouse and touch
+#[derive(Component)]
+pub struct PauseButton;
+
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (pause_game, pause_on_gamepad_disconnect).run_if(in_state(GameState::InGame)),
+        )
+        .add_systems(OnEnter(GameState::InGame), spawn_pause_button)
+        .add_systems(OnExit(GameState::InGame), despawn_pause_button)
+        .add_systems(OnEnter(GameState::Paused), freeze_game)
+        .add_systems(OnExit(GameState::Paused), unfreeze_game)
+        .add_systems(OnEnter(MenuState::Pause), spawn_pause_menu)
+        .add_systems(OnExit(MenuState::Pause), despawn_menu)
+        .add_systems(
+            Update,
+            // restart waits for the skeleton, like starting from the main menu
+            handle_pause_menu
+                .after(navigate_menu)
+                .run_if(in_state(MenuState::Pause).and(skeleton_loaded)),
+        );
+    }
+}
+
+pub fn pause_game(
+    kb_input: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    action_input: Res<ActionInput>,
+    button_query: Query<&Interaction, (Changed<Interaction>, With<PauseButton>)>,
+    jumpscare_query: Query<(), With<Jumpscare>>,
+    playback: Option<Res<ReplayPlayback>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    // dismiss closes the jumpscare first
+    let dismiss = action_input.just_pressed(Action::Dismiss) && jumpscare_query.is_empty();
+    // p pauses the replay itself during playback
+    let p = kb_input.just_pressed(KeyCode::KeyP) && playback.is_none();
+    let start = gamepads
+        .iter()
+        .any(|gamepad| gamepad.just_pressed(GamepadButton::Start));
+
+    let clicked = button_query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed);
+
+    if dismiss || p || start || clicked {
+        next_state.set(GameState::Paused);
+    }
+}
+
+pub fn spawn_pause_button(
+    mut commands: Commands,
+    theme_handle: Res<ThemeHandle>,
+    themes: Res<Assets<Theme>>,
+) {
+    let mut text_color = Color::srgb(1.0, 1.0, 1.0);
+
+    if let Some(theme) = themes.get(&theme_handle.0) {
+        text_color = theme.text_color.to_color();
+    }
+
+    commands.spawn((
+        Button,
+        Text::new("II"),
+        TextFont {
+            font_size: 28.0,
+            ..default()
+        },
+        TextColor(text_color),
+        Node {
+            position_type: PositionType::Absolute,
+            right: Val::Px(10.0),
+            top: Val::Px(10.0),
+            padding: UiRect::axes(Val::Px(12.0), Val::Px(4.0)),
+            ..default()
+        },
+        BackgroundColor(text_color.with_alpha(0.15)),
+        PauseButton,
+    ));
+}
+
+pub fn despawn_pause_button(mut commands: Commands, query: Query<Entity, With<PauseButton>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+pub fn pause_on_gamepad_disconnect(
+    mut connection_events: EventReader<GamepadConnectionEvent>,
+    playback: Option<Res<ReplayPlayback>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let disconnected = connection_events
+        .read()
+        .any(|event| matches!(event.connection, GamepadConnection::Disconnected));
+
+    // the player can not steer anymore, unless a replay is played back
+    if disconnected && playback.is_none() {
+        next_state.set(GameState::Paused);
+    }
+}
+
+pub fn freeze_game(
+    mut time: ResMut<Time<Virtual>>,
+    mut rapier_config_query: Query<&mut RapierConfiguration>,
+    mut next_menu: ResMut<NextState<MenuState>>,
+) {
+    // stops fixed ticks and the obstacle spawn timer
+    time.pause();
+    for mut rapier_config in rapier_config_query.iter_mut() {
+        rapier_config.physics_pipeline_active = false;
+    }
+    next_menu.set(MenuState::Pause);
+    info!("Game paused.");
+}
+
+pub fn unfreeze_game(
+    mut time: ResMut<Time<Virtual>>,
+    mut rapier_config_query: Query<&mut RapierConfiguration>,
+    playback: Option<Res<ReplayPlayback>>,
+    mut next_menu: ResMut<NextState<MenuState>>,
+) {
+    if !playback.is_some_and(|playback| playback.paused) {
+        time.unpause();
+    }
+    for mut rapier_config in rapier_config_query.iter_mut() {
+        rapier_config.physics_pipeline_active = true;
+    }
+    next_menu.set(MenuState::Closed);
+}
+
+pub fn spawn_pause_menu(
+    mut commands: Commands,
+    theme_handle: Res<ThemeHandle>,
+    themes: Res<Assets<Theme>>,
+) {
+    let mut text_color = Color::srgb(1.0, 1.0, 1.0);
+
+    if let Some(theme) = themes.get(&theme_handle.0) {
+        text_color = theme.text_color.to_color();
+    }
+
+    let items = [
+        ("Resume".to_string(), PauseAction::Resume),
+        ("Restart".to_string(), PauseAction::Restart),
+        ("Settings".to_string(), PauseAction::Settings),
+        ("Quit".to_string(), PauseAction::Quit),
+    ];
+
+    spawn_menu(&mut commands, "Paused", &items, text_color);
+}
+
+pub fn handle_pause_menu(
+    mut commands: Commands,
+    kb_input: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    action_input: Res<ActionInput>,
+    mut pressed_events: EventReader<MenuPressed>,
+    action_query: Query<&PauseAction>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut next_menu: ResMut<NextState<MenuState>>,
+    mut app_exit: EventWriter<AppExit>,
+) {
+    let start = gamepads
+        .iter()
+        .any(|gamepad| gamepad.just_pressed(GamepadButton::Start));
+
+    if action_input.just_pressed(Action::Dismiss) || kb_input.just_pressed(KeyCode::KeyP) || start {
+        next_state.set(GameState::InGame);
+        return;
+    }
+
+    for event in pressed_events.read() {
+        match action_query.get(event.0) {
+            Ok(PauseAction::Resume) => next_state.set(GameState::InGame),
+            Ok(PauseAction::Restart) => {
+                commands.run_schedule(NewRun);
+                next_state.set(GameState::InGame);
+            }
+            Ok(PauseAction::Settings) => next_menu.set(MenuState::Settings),
+            Ok(PauseAction::Quit) => {
+                app_exit.write(AppExit::Success);
+            }
+            Err(_) => {}
+        }
+    }
+}
@@ -0,0 +1,440 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game_states::{GameState, NewRun},
+    hazards::handle_hazard_collisions,
+    player::{PlayerData, handle_collision},
+    player_setup::{PlayerBodyPart, PlayerTorso},
+    themes::{Theme, ThemeHandle},
+    time_scale::TimeScale,
+};
+
+// score for collecting a coin
+const COIN_SCORE: i32 = 250;
+// seconds the player can not be hurt after the shield absorbed a hit, so a single crash only costs it once
+const SHIELD_GRACE: f32 = 0.5;
+// seconds of game time, which runs at SLOW_MOTION_SPEED meanwhile
+const SLOW_MOTION_DURATION: f32 = 3.0;
+const SLOW_MOTION_SPEED: f32 = 0.5;
+const MAGNET_DURATION: f32 = 8.0;
+// coins closer to the torso than this are pulled in
+const MAGNET_RADIUS: f32 = 400.0;
+const MAGNET_SPEED: f32 = 900.0;
+// pickups are removed once they are this far above the torso
+const OVER_PLAYER_DESPAWN: f32 = 1000.0;
+pub const PICKUP_RADIUS: f32 = 16.0;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, Reflect)]
+pub enum PickupKind {
+    // adds to the score
+    Coin,
+    // absorbs the next hit
+    Shield,
+    // heals a broken body part, kept until a part breaks if none is broken
+    Splint,
+    // slows the game down for a few seconds
+    SlowMotion,
+    // pulls coins towards the player for a few seconds
+    Magnet,
+}
+
+/// Something the player can collect by touching it.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Pickup(pub PickupKind);
+
+/// Effects of collected power-ups.
+#[derive(Resource, Default, Debug)]
+pub struct PowerUps {
+    pub shield: bool,
+    // seconds the player can not be hurt after the shield absorbed a hit
+    pub invulnerable: f32,
+    // splints waiting for a broken body part
+    pub splints: u32,
+    // seconds left for each effect
+    pub slow_motion: f32,
+    pub magnet: f32,
+}
+
+// icon of an active power-up in the hud
+#[derive(Component)]
+pub struct PowerUpIcon(pub PickupKind);
+
+pub struct PickupPlugin {
+    // skips the hud and the time scaling of slow motion when true
+    pub headless: bool,
+}
+
+impl Plugin for PickupPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PowerUps>()
+            .add_systems(NewRun, clear_pickups)
+            .add_systems(
+                FixedUpdate,
+                (tick_power_ups, pull_coins, despawn_passed_pickups)
+                    .chain()
+                    .before(PhysicsSet::SyncBackend)
+                    .run_if(in_state(GameState::InGame)),
+            )
+            .add_systems(
+                FixedUpdate,
+                (collect_pickups, apply_splints)
+                    .chain()
+                    .after(handle_collision)
+                    .after(handle_hazard_collisions)
+                    .run_if(in_state(GameState::InGame)),
+            );
+
+        if !self.headless {
+            app.add_systems(PostStartup, spawn_power_up_hud)
+                .add_systems(Update, apply_slow_motion)
+                .add_systems(PostUpdate, update_power_up_hud);
+        }
+    }
+}
+
+impl PickupKind {
+    pub const POWER_UPS: [PickupKind; 4] = [
+        PickupKind::Shield,
+        PickupKind::Splint,
+        PickupKind::SlowMotion,
+        PickupKind::Magnet,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PickupKind::Coin => "Coin",
+            PickupKind::Shield => "Shield",
+            PickupKind::Splint => "Splint",
+            PickupKind::SlowMotion => "Slow-mo",
+            PickupKind::Magnet => "Magnet",
+        }
+    }
+
+    pub fn mesh(&self) -> Mesh {
+        match self {
+            PickupKind::Coin => Circle::new(PICKUP_RADIUS * 0.75).into(),
+            PickupKind::Shield => RegularPolygon::new(PICKUP_RADIUS, 6).into(),
+            PickupKind::Splint => Capsule2d::new(PICKUP_RADIUS * 0.35, PICKUP_RADIUS * 1.3).into(),
+            PickupKind::SlowMotion => RegularPolygon::new(PICKUP_RADIUS, 3).into(),
+            PickupKind::Magnet => Annulus::new(PICKUP_RADIUS * 0.5, PICKUP_RADIUS).into(),
+        }
+    }
+
+    pub fn color(&self, theme: Option<&Theme>) -> Color {
+        match (self, theme) {
+            (PickupKind::Coin, Some(theme)) => theme.coin_color.to_color(),
+            (PickupKind::Shield, Some(theme)) => theme.shield_color.to_color(),
+            (PickupKind::Splint, Some(theme)) => theme.splint_color.to_color(),
+            (PickupKind::SlowMotion, Some(theme)) => theme.slow_motion_color.to_color(),
+            (PickupKind::Magnet, Some(theme)) => theme.magnet_color.to_color(),
+            (PickupKind::Coin, None) => Color::srgb(1.0, 0.85, 0.2),
+            (PickupKind::Shield, None) => Color::srgb(0.3, 0.8, 1.0),
+            (PickupKind::Splint, None) => Color::srgb(0.9, 0.75, 0.55),
+            (PickupKind::SlowMotion, None) => Color::srgb(0.7, 0.4, 1.0),
+            (PickupKind::Magnet, None) => Color::srgb(0.9, 0.2, 0.5),
+        }
+    }
+
+    pub fn material(&self, theme: Option<&Theme>, asset_server: &AssetServer) -> ColorMaterial {
+        let texture = theme.and_then(|theme| theme.pickup_textures.get(self));
+        match texture {
+            Some(texture) => ColorMaterial::from(asset_server.load(format!("themes/{}", texture))),
+            None => ColorMaterial::from(self.color(theme)),
+        }
+    }
+}
+
+impl PowerUps {
+    /// Uses up the shield for a hit, returns true if the hit should be ignored.
+    pub fn absorb_hit(&mut self) -> bool {
+        if self.invulnerable > 0.0 {
+            return true;
+        }
+        if self.shield {
+            self.shield = false;
+            self.invulnerable = SHIELD_GRACE;
+            info!("Shield absorbed a hit.");
+            return true;
+        }
+        false
+    }
+}
+
+pub fn spawn_pickup(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    asset_server: &AssetServer,
+    kind: PickupKind,
+    position: Vec2,
+    theme_opt: Option<&Theme>,
+) {
+    commands
+        .spawn(Mesh2d(meshes.add(kind.mesh())))
+        .insert(MeshMaterial2d(
+            materials.add(kind.material(theme_opt, asset_server)),
+        ))
+        .insert(Pickup(kind))
+        .insert(Collider::ball(PICKUP_RADIUS))
+        .insert(Sensor)
+        .insert(ActiveEvents::COLLISION_EVENTS)
+        .insert(Transform::from_xyz(position.x, position.y, 0.5))
+        .insert(Name::new(format!("pickup_{:?}", kind).to_lowercase()));
+}
+
+pub fn clear_pickups(
+    mut commands: Commands,
+    pickup_query: Query<Entity, With<Pickup>>,
+    mut power_ups: ResMut<PowerUps>,
+) {
+    for pickup in pickup_query.iter() {
+        commands.entity(pickup).despawn();
+    }
+    *power_ups = PowerUps::default();
+}
+
+pub fn tick_power_ups(mut power_ups: ResMut<PowerUps>, time: Res<Time>) {
+    let delta = time.delta_secs();
+    power_ups.invulnerable = (power_ups.invulnerable - delta).max(0.0);
+    power_ups.slow_motion = (power_ups.slow_motion - delta).max(0.0);
+    power_ups.magnet = (power_ups.magnet - delta).max(0.0);
+}
+
+pub fn pull_coins(
+    mut pickup_query: Query<(&Pickup, &mut Transform), Without<PlayerTorso>>,
+    player_query: Query<&Transform, With<PlayerTorso>>,
+    power_ups: Res<PowerUps>,
+    time: Res<Time>,
+) {
+    if power_ups.magnet <= 0.0 {
+        return;
+    }
+    let Ok(player_transform) = player_query.single() else {
+        return;
+    };
+    let target = player_transform.translation.truncate();
+
+    for (pickup, mut transform) in pickup_query.iter_mut() {
+        let offset = target - transform.translation.truncate();
+        if pickup.0 == PickupKind::Coin && offset.length() < MAGNET_RADIUS {
+            let step = offset.clamp_length_max(MAGNET_SPEED * time.delta_secs());
+            transform.translation += step.extend(0.0);
+        }
+    }
+}
+
+pub fn despawn_passed_pickups(
+    mut commands: Commands,
+    pickup_query: Query<(Entity, &Transform), With<Pickup>>,
+    player_query: Query<&Transform, With<PlayerTorso>>,
+) {
+    let Ok(player_transform) = player_query.single() else {
+        return;
+    };
+    for (pickup_entity, transform) in pickup_query.iter() {
+        if transform.translation.y >= player_transform.translation.y + OVER_PLAYER_DESPAWN {
+            commands.entity(pickup_entity).despawn();
+        }
+    }
+}
+
+pub fn collect_pickups(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    pickup_query: Query<&Pickup>,
+    part_query: Query<(), With<PlayerBodyPart>>,
+    mut power_ups: ResMut<PowerUps>,
+    mut player_data: ResMut<PlayerData>,
+    mut collected: Local<Vec<Entity>>,
+) {
+    collected.clear();
+
+    for collision_event in collision_events.read() {
+        let CollisionEvent::Started(collider1, collider2, _) = *collision_event else {
+            continue;
+        };
+
+        // the pickup and the body part can be in either order
+        let Some((pickup_entity, pickup)) = [(collider1, collider2), (collider2, collider1)]
+            .into_iter()
+            .find_map(|(pickup_entity, part)| {
+                let pickup = pickup_query.get(pickup_entity).ok()?;
+                part_query.get(part).ok()?;
+                Some((pickup_entity, *pickup))
+            })
+        else {
+            continue;
+        };
+
+        // several body parts can touch the same pickup in one tick
+        if collected.contains(&pickup_entity) {
+            continue;
+        }
+        collected.push(pickup_entity);
+        commands.entity(pickup_entity).despawn();
+
+        match pickup.0 {
+            PickupKind::Coin => player_data.score += COIN_SCORE,
+            PickupKind::Shield => power_ups.shield = true,
+            PickupKind::Splint => power_ups.splints += 1,
+            PickupKind::SlowMotion => power_ups.slow_motion = SLOW_MOTION_DURATION,
+            PickupKind::Magnet => power_ups.magnet = MAGNET_DURATION,
+        }
+        info!("Collected {}.", pickup.0.name());
+    }
+}
+
+pub fn apply_splints(
+    mut power_ups: ResMut<PowerUps>,
+    mut player_data: ResMut<PlayerData>,
+    part_query: Query<(&Name, &MeshMaterial2d<ColorMaterial>, &PlayerBodyPart)>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    theme_handle: Res<ThemeHandle>,
+    themes: Res<Assets<Theme>>,
+) {
+    if power_ups.splints == 0 {
+        return;
+    }
+
+    // sorted, so replays heal the same part. a broken fatal part already ended the run.
+    let is_fatal = |part_name: &String| {
+        part_query
+            .iter()
+            .any(|(name, _, part)| part.fatal && name.as_str() == part_name)
+    };
+    let Some(part_name) = player_data
+        .broken_parts
+        .iter()
+        .filter(|part_name| !is_fatal(part_name))
+        .min()
+        .cloned()
+    else {
+        return;
+    };
+
+    power_ups.splints -= 1;
+    player_data.broken_parts.remove(&part_name);
+
+    let mut body_color = Color::srgb(1.0, 1.0, 1.0);
+    if let Some(theme) = themes.get(&theme_handle.0) {
+        body_color = theme.player_body_color.to_color();
+    }
+    for (name, material_handle, _) in part_query.iter() {
+        if name.as_str() == part_name
+            && let Some(material) = materials.get_mut(&material_handle.0)
+        {
+            material.color = body_color;
+        }
+    }
+    info!("Splinted {}.", part_name);
+}
+
+pub fn apply_slow_motion(
+    mut time_scale: ResMut<TimeScale>,
+    power_ups: Res<PowerUps>,
+    game_state: Res<State<GameState>>,
+) {
+    let in_run = matches!(game_state.get(), GameState::InGame | GameState::Paused);
+    time_scale.slow_motion = if in_run && power_ups.slow_motion > 0.0 {
+        SLOW_MOTION_SPEED
+    } else {
+        1.0
+    };
+}
+
+pub fn spawn_power_up_hud(mut commands: Commands) {
+    commands
+        .spawn(Node {
+            position_type: PositionType::Absolute,
+            right: Val::Px(10.0),
+            top: Val::Px(60.0),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::End,
+            row_gap: Val::Px(6.0),
+            ..default()
+        })
+        .with_children(|hud| {
+            for kind in PickupKind::POWER_UPS {
+                hud.spawn((
+                    Node {
+                        display: Display::None,
+                        align_items: AlignItems::Center,
+                        column_gap: Val::Px(6.0),
+                        ..default()
+                    },
+                    PowerUpIcon(kind),
+                ))
+                .with_children(|icon| {
+                    icon.spawn((
+                        Text::new(""),
+                        TextFont {
+                            font_size: 18.0,
+                            ..default()
+                        },
+                        TextColor(Color::srgb(1.0, 1.0, 1.0)),
+                    ));
+                    icon.spawn((
+                        Node {
+                            width: Val::Px(16.0),
+                            height: Val::Px(16.0),
+                            ..default()
+                        },
+                        BorderRadius::MAX,
+                        BackgroundColor(kind.color(None)),
+                    ));
+                });
+            }
+        });
+}
+
+pub fn update_power_up_hud(
+    mut icon_query: Query<(&PowerUpIcon, &mut Node, &Children)>,
+    mut text_query: Query<&mut Text>,
+    mut background_query: Query<&mut BackgroundColor>,
+    power_ups: Res<PowerUps>,
+    game_state: Res<State<GameState>>,
+    theme_handle: Res<ThemeHandle>,
+    themes: Res<Assets<Theme>>,
+) {
+    let in_run = matches!(game_state.get(), GameState::InGame | GameState::Paused);
+    let theme = themes.get(&theme_handle.0);
+
+    for (icon, mut node, children) in icon_query.iter_mut() {
+        let label = match icon.0 {
+            PickupKind::Shield if power_ups.shield => Some(icon.0.name().to_string()),
+            PickupKind::Splint if power_ups.splints > 0 => {
+                Some(format!("{} x{}", icon.0.name(), power_ups.splints))
+            }
+            PickupKind::SlowMotion if power_ups.slow_motion > 0.0 => {
+                Some(format!("{} {:.1}", icon.0.name(), power_ups.slow_motion))
+            }
+            PickupKind::Magnet if power_ups.magnet > 0.0 => {
+                Some(format!("{} {:.1}", icon.0.name(), power_ups.magnet))
+            }
+            _ => None,
+        };
+
+        let display = match (&label, in_run) {
+            (Some(_), true) => Display::Flex,
+            _ => Display::None,
+        };
+        if node.display != display {
+            node.display = display;
+        }
+
+        for child in children.iter() {
+            if let (Some(label), Ok(mut text)) = (&label, text_query.get_mut(child))
+                && text.0 != *label
+            {
+                text.0 = label.clone();
+            }
+            if let Ok(mut background) = background_query.get_mut(child)
+                && background.0 != icon.0.color(theme)
+            {
+                background.0 = icon.0.color(theme);
+            }
+        }
+    }
+}
@@ -1,13 +1,19 @@
 use std::collections::HashSet;
 
-use bevy::input::ButtonInput;
 use bevy::prelude::*;
 use bevy_kira_audio::prelude::*;
 use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    game_states::GameState,
-    player_setup::{PlayerBodyPart, PlayerTorso},
+    actions::{Action, ActionInput, ActionSystem},
+    environment::ObstaclesData,
+    game_states::{GameState, NewRun, clear_run},
+    hazards::Hazard,
+    obstacles::MovingObstacle,
+    pickups::PowerUps,
+    player_setup::{PLAYER_START_Y, PlayerBodyPart, PlayerTorso, setup_player},
+    replay::ReplayPlayback,
     themes::{Theme, ThemeHandle},
 };
 
@@ -19,17 +25,81 @@ pub struct PlayerData {
     pub score: i32,
 }
 
+// input for the current tick, read from the keyboard or fed from a replay
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, Default, Debug, PartialEq)]
+pub struct PlayerInput {
+    pub left: bool,
+    pub right: bool,
+    // analog steering from -1.0 to 1.0, overrides left and right when not 0
+    #[serde(default)]
+    pub stick: f32,
+    // world x the torso steers toward, overrides the stick and keys when set
+    #[serde(default)]
+    pub pointer_x: Option<f32>,
+    // stays set until it is handled in the next fixed tick
+    pub reset: bool,
+}
+
+// total distance the world was moved up by recenter_world
+#[derive(Resource, Default)]
+pub struct WorldShift(pub f64);
+
+pub struct PlayerPlugin;
+
+impl Plugin for PlayerPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(PlayerData {
+            broken_parts: HashSet::new(),
+            last_death_str: "".to_string(),
+            last_y_position: PLAYER_START_Y,
+            score: 0,
+        })
+        .add_systems(Startup, setup_player)
+        .add_systems(NewRun, setup_player.after(clear_run))
+        .init_resource::<PlayerInput>()
+        .init_resource::<WorldShift>()
+        .add_systems(
+            PreUpdate,
+            read_player_input
+                .after(ActionSystem)
+                .run_if(in_state(GameState::InGame))
+                .run_if(not(resource_exists::<ReplayPlayback>)),
+        )
+        .add_systems(OnEnter(GameState::InGame), clear_player_input)
+        .add_systems(
+            FixedUpdate,
+            (handle_reset, player_control)
+                .chain()
+                .before(PhysicsSet::SyncBackend)
+                .run_if(in_state(GameState::InGame)),
+        )
+        .add_systems(
+            FixedUpdate,
+            (
+                handle_collision.run_if(in_state(GameState::InGame)),
+                recenter_world,
+                increment_score.run_if(in_state(GameState::InGame)),
+            )
+                .chain()
+                .after(PhysicsSet::Writeback),
+        );
+    }
+}
+
 pub fn handle_collision(
     mut contact_force_events: EventReader<ContactForceEvent>,
     name_query: Query<&Name>,
+    part_query: Query<&PlayerBodyPart>,
+    hazard_query: Query<&Hazard>,
     mut player_data: ResMut<PlayerData>,
     mut color_query: Query<&mut MeshMaterial2d<ColorMaterial>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
     mut game_state: ResMut<NextState<GameState>>,
     theme_handle: Res<ThemeHandle>,
     themes: Res<Assets<Theme>>,
-    audio_player: Res<Audio>,
+    audio_player: Option<Res<Audio>>,
     asset_server: Res<AssetServer>,
+    mut power_ups: ResMut<PowerUps>,
 ) {
     let mut broken_color = Color::srgb(1.0, 1.0, 0.2);
     let mut final_color = Color::srgb(1.0, 0.2, 0.2);
@@ -46,12 +116,27 @@ pub fn handle_collision(
     }
 
     for contact_force_event in contact_force_events.read() {
+        // spikes and bouncy pads have their own effect instead of the usual damage
+        let replaces_damage = [contact_force_event.collider1, contact_force_event.collider2]
+            .into_iter()
+            .any(|collider| {
+                hazard_query
+                    .get(collider)
+                    .is_ok_and(|hazard| hazard.replaces_damage())
+            });
+        if replaces_damage {
+            continue;
+        }
+
         let name1 = name_query
             .get(contact_force_event.collider1)
             .map_or("not_found", |n| n);
         let name2 = name_query
             .get(contact_force_event.collider2)
             .map_or("not_found", |n| n);
+        let is_fatal = |collider| part_query.get(collider).is_ok_and(|part| part.fatal);
+        let fatal1 = is_fatal(contact_force_event.collider1);
+        let fatal2 = is_fatal(contact_force_event.collider2);
 
         let impact_force = contact_force_event.total_force_magnitude as i32;
 
@@ -60,12 +145,17 @@ pub fn handle_collision(
                 "Collision between '{}' and '{}'. Force: {}",
                 name1, name2, impact_force
             );
+            if power_ups.absorb_hit() {
+                continue;
+            }
             if player_data.broken_parts.contains(name1) || player_data.broken_parts.contains(name2)
             {
                 if !played_sound {
-                    audio_player
-                        .play(asset_server.load(&sound_path))
-                        .with_volume(Volume::Amplitude(0.5));
+                    if let Some(audio_player) = &audio_player {
+                        audio_player
+                            .play(asset_server.load(&sound_path))
+                            .with_volume(Volume::Amplitude(0.5));
+                    }
                     played_sound = true;
                 }
                 game_state.set(GameState::GameOver);
@@ -73,13 +163,15 @@ pub fn handle_collision(
                 info!("Player hit ground too hard.");
             }
             if name1.contains("player") {
-                if !player_data.broken_parts.contains(name1) && !name1.contains("player_head") {
+                if !player_data.broken_parts.contains(name1) && !fatal1 {
                     player_data.broken_parts.insert(name1.to_string());
                     if let Ok(material_handle) = color_query.get_mut(contact_force_event.collider1)
                         && let Some(material) = materials.get_mut(&material_handle.0)
                     {
                         if !played_sound {
-                            audio_player.play(asset_server.load(&sound_path));
+                            if let Some(audio_player) = &audio_player {
+                                audio_player.play(asset_server.load(&sound_path));
+                            }
                             played_sound = true;
                         }
                         material.color = broken_color;
@@ -90,7 +182,9 @@ pub fn handle_collision(
                         && let Some(material) = materials.get_mut(&material_handle.0)
                     {
                         if !played_sound {
-                            audio_player.play(asset_server.load(&sound_path));
+                            if let Some(audio_player) = &audio_player {
+                                audio_player.play(asset_server.load(&sound_path));
+                            }
                             played_sound = true;
                         }
                         material.color = final_color;
@@ -98,13 +192,15 @@ pub fn handle_collision(
                 }
             }
             if name2.contains("player") {
-                if !player_data.broken_parts.contains(name2) && !name2.contains("player_head") {
+                if !player_data.broken_parts.contains(name2) && !fatal2 {
                     player_data.broken_parts.insert(name2.to_string());
                     if let Ok(material_handle) = color_query.get_mut(contact_force_event.collider2)
                         && let Some(material) = materials.get_mut(&material_handle.0)
                     {
                         if !played_sound {
-                            audio_player.play(asset_server.load(&sound_path));
+                            if let Some(audio_player) = &audio_player {
+                                audio_player.play(asset_server.load(&sound_path));
+                            }
                             played_sound = true;
                         }
                         material.color = broken_color;
@@ -115,21 +211,29 @@ pub fn handle_collision(
                         && let Some(material) = materials.get_mut(&material_handle.0)
                     {
                         if !played_sound {
-                            audio_player.play(asset_server.load(&sound_path));
+                            if let Some(audio_player) = &audio_player {
+                                audio_player.play(asset_server.load(&sound_path));
+                            }
                             played_sound = true;
                         }
                         material.color = final_color;
                     }
                 }
             }
-            if player_data.broken_parts.contains("player_head") {
+            let fatal_part = [(name1, fatal1), (name2, fatal2)]
+                .into_iter()
+                .find(|(name, fatal)| *fatal && player_data.broken_parts.contains(*name));
+            if let Some((fatal_name, _)) = fatal_part {
                 if !played_sound {
-                    audio_player.play(asset_server.load(&sound_path));
+                    if let Some(audio_player) = &audio_player {
+                        audio_player.play(asset_server.load(&sound_path));
+                    }
                     played_sound = true;
                 }
                 game_state.set(GameState::GameOver);
-                player_data.last_death_str = "You hit your head too hard.".to_string();
-                info!("Player died because head was hit too hard.");
+                let part_name = fatal_name.trim_start_matches("player_").replace('_', " ");
+                player_data.last_death_str = format!("You hit your {} too hard.", part_name);
+                info!("Player died because {} was hit too hard.", part_name);
             }
         }
     }
@@ -148,61 +252,123 @@ pub fn increment_score(
 
 const MOVE_ACCELERATION: f32 = 100.0;
 const MAX_MOVE_SPEED: f32 = 600.0;
+// pointer steering speed per pixel between the torso and the pointer
+const POINTER_STEER_GAIN: f32 = 5.0;
+
+pub fn clear_player_input(mut input: ResMut<PlayerInput>) {
+    *input = PlayerInput::default();
+}
+
+pub fn read_player_input(action_input: Res<ActionInput>, mut input: ResMut<PlayerInput>) {
+    input.left = action_input.pressed(Action::MoveLeft);
+    input.right = action_input.pressed(Action::MoveRight);
+    input.stick = action_input.axis();
+    input.pointer_x = action_input.pointer().map(|pointer| pointer.x);
+    if action_input.just_pressed(Action::Reset) {
+        input.reset = true;
+    }
+}
 
 pub fn player_control(
-    mut player_query: Query<&mut Velocity, With<PlayerTorso>>,
-    kb_input: Res<ButtonInput<KeyCode>>,
-    mut game_state: ResMut<NextState<GameState>>,
-    mut player_data: ResMut<PlayerData>,
+    mut player_query: Query<(&mut Velocity, &Transform), With<PlayerTorso>>,
+    input: Res<PlayerInput>,
 ) {
-    if kb_input.pressed(KeyCode::ArrowRight) {
-        if let Ok(mut velocity) = player_query.single_mut() {
-            if velocity.linvel.x <= MAX_MOVE_SPEED - MOVE_ACCELERATION {
-                velocity.linvel.x += MOVE_ACCELERATION;
-            } else {
-                velocity.linvel.x = MAX_MOVE_SPEED;
-            }
+    // the target speed drops off near the pointer, so the torso settles below it
+    if let Some(pointer_x) = input.pointer_x {
+        if let Ok((mut velocity, transform)) = player_query.single_mut() {
+            let target_speed = ((pointer_x - transform.translation.x) * POINTER_STEER_GAIN)
+                .clamp(-MAX_MOVE_SPEED, MAX_MOVE_SPEED);
+            velocity.linvel.x +=
+                (target_speed - velocity.linvel.x).clamp(-MOVE_ACCELERATION, MOVE_ACCELERATION);
         }
-    } else if kb_input.pressed(KeyCode::ArrowLeft) {
-        if let Ok(mut velocity) = player_query.single_mut() {
-            if velocity.linvel.x >= -(MAX_MOVE_SPEED - MOVE_ACCELERATION) {
-                velocity.linvel.x -= MOVE_ACCELERATION;
-            } else {
-                velocity.linvel.x = -MAX_MOVE_SPEED;
-            }
+        return;
+    }
+
+    // the stick accelerates proportionally, keys always use the full acceleration
+    let steer = if input.stick != 0.0 {
+        input.stick
+    } else if input.right {
+        1.0
+    } else if input.left {
+        -1.0
+    } else {
+        return;
+    };
+
+    if let Ok((mut velocity, _)) = player_query.single_mut() {
+        let acceleration = MOVE_ACCELERATION * steer;
+        if steer > 0.0 {
+            velocity.linvel.x = (velocity.linvel.x + acceleration).min(MAX_MOVE_SPEED);
+        } else {
+            velocity.linvel.x = (velocity.linvel.x + acceleration).max(-MAX_MOVE_SPEED);
         }
-    } else if kb_input.just_pressed(KeyCode::KeyR) {
+    }
+}
+
+pub fn handle_reset(
+    mut input: ResMut<PlayerInput>,
+    mut game_state: ResMut<NextState<GameState>>,
+    mut player_data: ResMut<PlayerData>,
+) {
+    if input.reset {
+        input.reset = false;
         game_state.set(GameState::GameOver);
         player_data.last_death_str = "You reset the game.".to_string();
         info!("Player reset game.")
     }
 }
 
-const RESET_HEIGHT: f32 = 5000.0;
+// the world is moved back to the origin once the torso falls below this
 const MIN_HEIGHT: f32 = -5000.0;
 
+/// Moves the whole world up so the torso is back at the origin, which keeps positions small
+/// enough for f32 however far the player falls.
+/// Everything that keeps world positions has to be moved here as well.
 pub fn recenter_world(
     mut transforms: ParamSet<(
         Query<&Transform, With<PlayerTorso>>,
-        Query<&mut Transform, With<PlayerBodyPart>>,
+        // children move with their parent and ui nodes are not in the world
+        Query<&mut Transform, (Without<ChildOf>, Without<Node>)>,
     )>,
+    mut moving_query: Query<(&mut MovingObstacle, Option<&RapierRigidBodyHandle>)>,
+    mut rapier_context: WriteRapierContext,
+    mut obstacles_data: ResMut<ObstaclesData>,
     mut player_data: ResMut<PlayerData>,
-    game_state: Res<State<GameState>>,
+    mut world_shift: ResMut<WorldShift>,
 ) {
-    if let Ok(torso_transform) = transforms.p0().single() {
-        let torso_y = torso_transform.translation.y;
+    let Ok(torso_transform) = transforms.p0().single() else {
+        return;
+    };
+    let torso_y = torso_transform.translation.y;
+    if torso_y >= MIN_HEIGHT {
+        return;
+    }
 
-        if torso_y < MIN_HEIGHT {
-            let diff = RESET_HEIGHT - torso_y;
+    // whole pixels keep positions exact, they only get closer to the origin
+    let diff = -torso_y.round();
 
-            for mut rigid_body in transforms.p1().iter_mut() {
-                rigid_body.translation.y += diff;
-            }
+    for mut transform in transforms.p1().iter_mut() {
+        transform.translation.y += diff;
+    }
 
-            if game_state.get() == &GameState::InGame {
-                player_data.last_y_position =
-                    RESET_HEIGHT + (player_data.last_y_position - MIN_HEIGHT).abs();
-            }
+    let mut context = rapier_context.single_mut().ok();
+    for (mut moving, body_handle) in moving_query.iter_mut() {
+        moving.path.shift(diff);
+
+        // rapier would move kinematic bodies there at the speed of the jump and fling the player
+        if let (Some(context), Some(body_handle)) = (&mut context, body_handle)
+            && let Some(body) = context.rigidbody_set.bodies.get_mut(body_handle.0)
+        {
+            let mut translation = *body.translation();
+            translation.y += diff;
+            body.set_translation(translation, true);
         }
     }
+
+    for piece in obstacles_data.pending.iter_mut() {
+        piece.position.y += diff;
+    }
+    player_data.last_y_position += diff;
+    world_shift.0 += diff as f64;
+    debug!("Moved the world up by {}.", diff);
 }
@@ -0,0 +1,298 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game_modes::GameMode,
+    game_states::{GameState, NewRun},
+    player::{PlayerData, PlayerInput, handle_reset},
+    seed::{RunSeed, roll_run_seed},
+    skeleton::{CharacterManifest, CurrentCharacterIndex},
+    time_scale::TimeScale,
+};
+
+const FAST_FORWARD_SPEED: f32 = 4.0;
+
+/// Seed and per-tick input of a single run.
+/// Feeding the input back into a run with the same seed plays it out again.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Replay {
+    pub seed: u64,
+    #[serde(default)]
+    pub mode: GameMode,
+    // name of the character, the default character if empty
+    #[serde(default)]
+    pub character: String,
+    pub score: i32,
+    pub death: String,
+    // number of fixed ticks the run lasted
+    pub ticks: u32,
+    // input is only stored for the ticks where it changed
+    pub inputs: Vec<ReplayInput>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct ReplayInput {
+    pub tick: u32,
+    pub input: PlayerInput,
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        Ok(ron::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, ron::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+pub fn replays_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("falling").join("replays"))
+}
+
+#[derive(Resource, Default)]
+pub struct ReplayRecorder {
+    replay: Replay,
+    tick: u32,
+    last_input: Option<PlayerInput>,
+}
+
+impl ReplayRecorder {
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+}
+
+/// Only exists while a replay is played back instead of reading the keyboard.
+#[derive(Resource)]
+pub struct ReplayPlayback {
+    pub replay: Replay,
+    pub tick: u32,
+    pub paused: bool,
+    pub fast_forward: bool,
+    cursor: usize,
+    current: PlayerInput,
+    stepping: bool,
+}
+
+impl ReplayPlayback {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            tick: 0,
+            paused: false,
+            fast_forward: false,
+            cursor: 0,
+            current: PlayerInput::default(),
+            stepping: false,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct ReplayText;
+
+pub struct ReplayPlugin {
+    // plays this replay back instead of recording new ones
+    pub playback: Option<Replay>,
+    // saves a replay file once a run is over
+    pub save: bool,
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        if let Some(replay) = &self.playback {
+            app.insert_resource(ReplayPlayback::new(replay.clone()))
+                .add_systems(PostStartup, spawn_replay_text)
+                .add_systems(NewRun, rewind_playback)
+                .add_systems(
+                    FixedUpdate,
+                    (feed_replay_input, finish_replay_step)
+                        .chain()
+                        .before(handle_reset)
+                        .run_if(in_state(GameState::InGame)),
+                )
+                .add_systems(
+                    Update,
+                    replay_controls.run_if(not(in_state(GameState::Paused))),
+                )
+                .add_systems(PostUpdate, update_replay_text);
+        } else {
+            app.init_resource::<ReplayRecorder>()
+                .add_systems(NewRun, start_recording.after(roll_run_seed))
+                .add_systems(
+                    FixedUpdate,
+                    record_input
+                        .before(handle_reset)
+                        .run_if(in_state(GameState::InGame)),
+                );
+            if self.save {
+                app.add_systems(OnEnter(GameState::GameOver), save_replay);
+            }
+        }
+    }
+}
+
+pub fn start_recording(
+    mut recorder: ResMut<ReplayRecorder>,
+    run_seed: Res<RunSeed>,
+    mode: Res<GameMode>,
+    character_index: Res<CurrentCharacterIndex>,
+) {
+    *recorder = ReplayRecorder::default();
+    recorder.replay.seed = run_seed.seed;
+    recorder.replay.mode = *mode;
+    if let Some(character) = CharacterManifest::builtin()
+        .characters
+        .get(character_index.0)
+    {
+        recorder.replay.character = character.name.clone();
+    }
+}
+
+pub fn record_input(mut recorder: ResMut<ReplayRecorder>, input: Res<PlayerInput>) {
+    if recorder.last_input != Some(*input) {
+        let tick = recorder.tick;
+        recorder.replay.inputs.push(ReplayInput {
+            tick,
+            input: *input,
+        });
+        recorder.last_input = Some(*input);
+    }
+    recorder.tick += 1;
+}
+
+pub fn save_replay(mut recorder: ResMut<ReplayRecorder>, player_data: Res<PlayerData>) {
+    recorder.replay.ticks = recorder.tick;
+    recorder.replay.score = player_data.score;
+    recorder.replay.death = player_data.last_death_str.clone();
+
+    let Some(dir) = replays_dir() else {
+        warn!("No data directory found, replay is not saved.");
+        return;
+    };
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let path = dir.join(format!("{}-{}.replay.ron", timestamp, recorder.replay.seed));
+
+    match recorder.replay.save(&path) {
+        Ok(()) => info!("Saved replay to {}.", path.display()),
+        Err(err) => warn!("Failed to save replay to {}: {}", path.display(), err),
+    }
+}
+
+pub fn rewind_playback(mut playback: ResMut<ReplayPlayback>) {
+    let replay = std::mem::take(&mut playback.replay);
+    let paused = playback.paused;
+    let fast_forward = playback.fast_forward;
+
+    *playback = ReplayPlayback::new(replay);
+    playback.paused = paused;
+    playback.fast_forward = fast_forward;
+    info!("Playing back replay with seed {}.", playback.replay.seed);
+}
+
+pub fn feed_replay_input(mut playback: ResMut<ReplayPlayback>, mut input: ResMut<PlayerInput>) {
+    let playback = &mut *playback;
+
+    while let Some(entry) = playback.replay.inputs.get(playback.cursor)
+        && entry.tick <= playback.tick
+    {
+        playback.current = entry.input;
+        playback.cursor += 1;
+    }
+
+    *input = playback.current;
+    playback.tick += 1;
+}
+
+pub fn replay_controls(
+    kb_input: Res<ButtonInput<KeyCode>>,
+    mut playback: ResMut<ReplayPlayback>,
+    mut input: ResMut<PlayerInput>,
+    mut time: ResMut<Time<Virtual>>,
+    mut time_scale: ResMut<TimeScale>,
+) {
+    if kb_input.just_pressed(KeyCode::KeyP) {
+        playback.paused = !playback.paused;
+    } else if kb_input.just_pressed(KeyCode::Period) && playback.paused {
+        playback.stepping = true;
+        time.unpause();
+        return;
+    } else if kb_input.just_pressed(KeyCode::KeyF) {
+        playback.fast_forward = !playback.fast_forward;
+    } else {
+        return;
+    }
+
+    if playback.paused {
+        time.pause();
+        // keeps the player from steering while no ticks are fed
+        *input = PlayerInput::default();
+    } else {
+        time.unpause();
+    }
+
+    time_scale.fast_forward = if playback.fast_forward {
+        FAST_FORWARD_SPEED
+    } else {
+        1.0
+    };
+}
+
+pub fn finish_replay_step(mut playback: ResMut<ReplayPlayback>, mut time: ResMut<Time<Virtual>>) {
+    // a step only lasts for a single tick
+    if playback.stepping {
+        playback.stepping = false;
+        time.pause();
+    }
+}
+
+pub fn spawn_replay_text(mut commands: Commands) {
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font_size: 18.0,
+            ..default()
+        },
+        TextColor(Color::srgb(1.0, 1.0, 1.0)),
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(10.0),
+            bottom: Val::Px(10.0),
+            ..default()
+        },
+        ReplayText,
+    ));
+}
+
+pub fn update_replay_text(
+    playback: Res<ReplayPlayback>,
+    mut text_query: Query<&mut Text, With<ReplayText>>,
+) {
+    if let Ok(mut text) = text_query.single_mut() {
+        let status = if playback.paused {
+            "Paused"
+        } else if playback.fast_forward {
+            "Fast-forward"
+        } else {
+            "Playing"
+        };
+
+        text.0 = format!(
+            "Replay: {} / {} ({})\nP: Pause  F: Fast-forward  .: Step",
+            playback.tick, playback.replay.ticks, status
+        );
+    }
+}
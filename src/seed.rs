@@ -0,0 +1,41 @@
+use bevy::prelude::*;
+
+use crate::game_modes::{GameMode, daily_seed};
+use crate::game_states::NewRun;
+
+/// Seed for all randomness of the current run.
+#[derive(Resource)]
+pub struct RunSeed {
+    pub seed: u64,
+    // set when a seed was chosen at launch, every run then uses it
+    pub fixed: bool,
+    // reuse the current seed for the next run only
+    pub retry: bool,
+}
+
+pub struct SeedPlugin {
+    pub seed: Option<u64>,
+}
+
+impl Plugin for SeedPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(RunSeed {
+            seed: self.seed.unwrap_or_default(),
+            fixed: self.seed.is_some(),
+            retry: false,
+        })
+        .add_systems(NewRun, roll_run_seed);
+    }
+}
+
+pub fn roll_run_seed(mut run_seed: ResMut<RunSeed>, mode: Res<GameMode>) {
+    if !run_seed.fixed && !run_seed.retry {
+        run_seed.seed = match *mode {
+            GameMode::Daily => daily_seed(),
+            _ => rand::random(),
+        };
+    }
+    run_seed.retry = false;
+
+    info!("Starting run with seed {}.", run_seed.seed);
+}
@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+
+use bevy::asset::Asset;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::reflect::Reflect;
+use bevy_common_assets::ron::RonAssetPlugin;
+use rand::{Rng, distributions::WeightedIndex, prelude::Distribution, rngs::StdRng};
+use serde::{Deserialize, Serialize};
+
+use crate::obstacles::{ObstacleCatalog, ObstacleCatalogs, ObstacleKind, load_obstacle_catalog};
+
+/// A group of obstacles with fixed positions relative to each other, made by hand.
+#[derive(Asset, Serialize, Deserialize, Clone, Debug, Reflect)]
+pub struct ObstacleSegment {
+    pub name: String,
+    // chance to be picked relative to the other segments
+    #[serde(default = "default_one")]
+    pub weight: f32,
+    // only spawns once the score reaches this
+    #[serde(default)]
+    pub min_score: i32,
+    // randomly flips the segment left to right if true
+    #[serde(default = "default_true")]
+    pub mirror: bool,
+    pub pieces: Vec<SegmentPiece>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Reflect)]
+pub struct SegmentPiece {
+    // name of the obstacle kind in the catalog
+    pub obstacle: String,
+    // 0.0 is the middle of the shaft
+    pub x: f32,
+    // distance below the top of the segment
+    pub y: f32,
+    // picked from the range of the kind if not set
+    #[serde(default)]
+    pub width: Option<f32>,
+    #[serde(default)]
+    pub height: Option<f32>,
+    // degrees counterclockwise, the kind decides if not set
+    #[serde(default)]
+    pub angle: Option<f32>,
+}
+
+/// Obstacle of a segment that is waiting to come close enough to the player to spawn.
+#[derive(Clone, Debug)]
+pub struct PendingPiece {
+    pub kind: ObstacleKind,
+    // moved along with the world by recenter_world
+    pub position: Vec2,
+    pub size: Vec2,
+    // radians, the kind decides if not set
+    pub angle: Option<f32>,
+}
+
+// segments requested from the asset server by path
+#[derive(Resource, Default)]
+pub struct SegmentHandles(pub HashMap<String, Handle<ObstacleSegment>>);
+
+// used until the segment assets are loaded, so the segments a seed picks do not depend on loading times
+#[derive(Resource)]
+pub struct BuiltinSegments(pub HashMap<String, ObstacleSegment>);
+
+/// The segments of the current catalog, built-in ones are used while they are loading.
+#[derive(SystemParam)]
+pub struct Segments<'w> {
+    handles: Res<'w, SegmentHandles>,
+    segments: Res<'w, Assets<ObstacleSegment>>,
+    builtin: Res<'w, BuiltinSegments>,
+}
+
+pub struct SegmentPlugin;
+
+impl Plugin for SegmentPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(RonAssetPlugin::<ObstacleSegment>::new(&["segment.ron"]))
+            .init_resource::<SegmentHandles>()
+            .insert_resource(BuiltinSegments(ObstacleSegment::builtin()))
+            .add_systems(PreStartup, load_segments.after(load_obstacle_catalog))
+            .add_systems(Update, load_segments);
+    }
+}
+
+/// Starts loading the segments the catalog lists, also when the catalog is changed.
+pub fn load_segments(
+    catalogs: ObstacleCatalogs,
+    mut handles: ResMut<SegmentHandles>,
+    asset_server: Res<AssetServer>,
+) {
+    for path in &catalogs.get().segments {
+        if !handles.0.contains_key(path) {
+            handles.0.insert(path.clone(), asset_server.load(path));
+        }
+    }
+}
+
+impl Segments<'_> {
+    /// Picks a segment that is unlocked at the score by its weight.
+    /// Segments that are not built in are left out until they are loaded.
+    /// Returns `None` if no such segment has a positive weight.
+    pub fn pick(
+        &self,
+        catalog: &ObstacleCatalog,
+        rng: &mut StdRng,
+        score: i32,
+    ) -> Option<&ObstacleSegment> {
+        // goes by the order of the catalog, so the same seed picks the same segments
+        let segments: Vec<_> = catalog
+            .segments
+            .iter()
+            .map(|path| {
+                self.handles
+                    .0
+                    .get(path)
+                    .and_then(|handle| self.segments.get(handle))
+                    .or_else(|| self.builtin.0.get(path))
+            })
+            .collect();
+        let weights = segments.iter().map(|segment| match segment {
+            Some(segment) if score >= segment.min_score => segment.weight,
+            _ => 0.0,
+        });
+        let index = WeightedIndex::new(weights).ok()?;
+        segments[index.sample(rng)]
+    }
+}
+
+impl ObstacleSegment {
+    /// Segments of the default catalog compiled into the binary, by path.
+    pub fn builtin() -> HashMap<String, Self> {
+        [
+            (
+                "segments/funnel.segment.ron",
+                include_str!("../assets/segments/funnel.segment.ron"),
+            ),
+            (
+                "segments/zigzag.segment.ron",
+                include_str!("../assets/segments/zigzag.segment.ron"),
+            ),
+            (
+                "segments/gate.segment.ron",
+                include_str!("../assets/segments/gate.segment.ron"),
+            ),
+            (
+                "segments/spike_gate.segment.ron",
+                include_str!("../assets/segments/spike_gate.segment.ron"),
+            ),
+        ]
+        .into_iter()
+        .map(|(path, source)| {
+            let segment = ron::from_str(source).expect("built-in segment is valid");
+            (path.to_string(), segment)
+        })
+        .collect()
+    }
+
+    /// Places the pieces below `top_y`, sorted from top to bottom.
+    /// Pieces of kinds that are not in the catalog are left out.
+    pub fn place(
+        &self,
+        catalog: &ObstacleCatalog,
+        rng: &mut StdRng,
+        size_scale: (f32, f32),
+        top_y: f32,
+    ) -> Vec<PendingPiece> {
+        let flip = if self.mirror && rng.gen_bool(0.5) {
+            -1.0
+        } else {
+            1.0
+        };
+
+        let mut pieces: Vec<_> = self
+            .pieces
+            .iter()
+            .filter_map(|piece| {
+                let Some(kind) = catalog.kind(&piece.obstacle) else {
+                    warn!(
+                        "Segment '{}' uses unknown obstacle '{}'.",
+                        self.name, piece.obstacle
+                    );
+                    return None;
+                };
+
+                let mut size = kind.pick_size(rng, size_scale);
+                size.x = piece.width.unwrap_or(size.x);
+                size.y = piece.height.unwrap_or(size.y);
+
+                Some(PendingPiece {
+                    kind: kind.clone(),
+                    position: Vec2::new(piece.x * flip, top_y - piece.y),
+                    size,
+                    angle: piece.angle.map(|angle| angle.to_radians() * flip),
+                })
+            })
+            .collect();
+        pieces.sort_by(|a, b| b.position.y.total_cmp(&a.position.y));
+        pieces
+    }
+}
+
+fn default_one() -> f32 {
+    1.0
+}
+
+fn default_true() -> bool {
+    true
+}
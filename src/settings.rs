@@ -0,0 +1,304 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use bevy::window::{MonitorSelection, PrimaryWindow, WindowMode};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actions::Action,
+    audio::AudioVolume,
+    game_modes::GameMode,
+    skeleton::{CharacterManifest, CurrentCharacterIndex},
+    themes::{CurrentThemeIndex, ThemeManifest},
+};
+
+/// Settings that are kept between restarts.
+/// Stored as RON in the platform config directory.
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    // name of the selected theme
+    pub theme: String,
+    // name of the selected character
+    pub character: String,
+    // 0.0 is muted, 1.0 is full volume
+    pub volume: f32,
+    pub fullscreen: bool,
+    // every run uses this seed if set
+    pub seed: Option<u64>,
+    pub game_mode: GameMode,
+    pub key_bindings: KeyBindings,
+    pub gamepad_bindings: GamepadBindings,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub move_left: KeyCode,
+    pub move_right: KeyCode,
+    pub reset: KeyCode,
+    pub retry: KeyCode,
+    pub next_theme: KeyCode,
+    pub confirm: KeyCode,
+    pub dismiss: KeyCode,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct GamepadBindings {
+    pub move_left: GamepadButton,
+    pub move_right: GamepadButton,
+    pub reset: GamepadButton,
+    pub retry: GamepadButton,
+    pub next_theme: GamepadButton,
+    pub confirm: GamepadButton,
+    pub dismiss: GamepadButton,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            theme: "Default".to_string(),
+            character: "Human".to_string(),
+            volume: 1.0,
+            fullscreen: false,
+            seed: None,
+            game_mode: GameMode::Classic,
+            key_bindings: KeyBindings::default(),
+            gamepad_bindings: GamepadBindings::default(),
+        }
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            move_left: KeyCode::ArrowLeft,
+            move_right: KeyCode::ArrowRight,
+            reset: KeyCode::KeyR,
+            retry: KeyCode::KeyS,
+            next_theme: KeyCode::Tab,
+            confirm: KeyCode::Space,
+            dismiss: KeyCode::Escape,
+        }
+    }
+}
+
+impl Default for GamepadBindings {
+    fn default() -> Self {
+        Self {
+            move_left: GamepadButton::DPadLeft,
+            move_right: GamepadButton::DPadRight,
+            reset: GamepadButton::West,
+            retry: GamepadButton::RightTrigger,
+            next_theme: GamepadButton::North,
+            confirm: GamepadButton::South,
+            dismiss: GamepadButton::East,
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn key(&self, action: Action) -> KeyCode {
+        match action {
+            Action::MoveLeft => self.move_left,
+            Action::MoveRight => self.move_right,
+            Action::Reset => self.reset,
+            Action::Retry => self.retry,
+            Action::NextTheme => self.next_theme,
+            Action::Confirm => self.confirm,
+            Action::Dismiss => self.dismiss,
+        }
+    }
+
+    pub fn set_key(&mut self, action: Action, key: KeyCode) {
+        let binding = match action {
+            Action::MoveLeft => &mut self.move_left,
+            Action::MoveRight => &mut self.move_right,
+            Action::Reset => &mut self.reset,
+            Action::Retry => &mut self.retry,
+            Action::NextTheme => &mut self.next_theme,
+            Action::Confirm => &mut self.confirm,
+            Action::Dismiss => &mut self.dismiss,
+        };
+        *binding = key;
+    }
+}
+
+impl GamepadBindings {
+    pub fn button(&self, action: Action) -> GamepadButton {
+        match action {
+            Action::MoveLeft => self.move_left,
+            Action::MoveRight => self.move_right,
+            Action::Reset => self.reset,
+            Action::Retry => self.retry,
+            Action::NextTheme => self.next_theme,
+            Action::Confirm => self.confirm,
+            Action::Dismiss => self.dismiss,
+        }
+    }
+
+    pub fn set_button(&mut self, action: Action, button: GamepadButton) {
+        let binding = match action {
+            Action::MoveLeft => &mut self.move_left,
+            Action::MoveRight => &mut self.move_right,
+            Action::Reset => &mut self.reset,
+            Action::Retry => &mut self.retry,
+            Action::NextTheme => &mut self.next_theme,
+            Action::Confirm => &mut self.confirm,
+            Action::Dismiss => &mut self.dismiss,
+        };
+        *binding = button;
+    }
+}
+
+impl Settings {
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("falling").join("settings.ron"))
+    }
+
+    /// Loads the settings file, falling back to defaults if it is missing or corrupt.
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else {
+            return Self::default();
+        };
+        if !path.exists() {
+            return Self::default();
+        }
+
+        match Self::read(&path) {
+            Ok(settings) => settings,
+            Err(err) => {
+                warn!(
+                    "Failed to read settings from {}, using defaults: {}",
+                    path.display(),
+                    err
+                );
+                Self::default()
+            }
+        }
+    }
+
+    fn read(path: &Path) -> Result<Self, Box<dyn Error>> {
+        Ok(ron::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let path = Self::path().ok_or("no config directory found")?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(
+            path,
+            ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?,
+        )?;
+        Ok(())
+    }
+}
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, apply_window_settings)
+            .add_systems(Update, toggle_fullscreen)
+            .add_systems(
+                Update,
+                (
+                    sync_theme_setting,
+                    sync_character_setting,
+                    sync_volume_setting,
+                    sync_game_mode_setting,
+                ),
+            )
+            .add_systems(Last, save_settings);
+    }
+}
+
+pub fn apply_window_settings(
+    settings: Res<Settings>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if settings.fullscreen
+        && let Ok(mut window) = window_query.single_mut()
+    {
+        window.mode = WindowMode::BorderlessFullscreen(MonitorSelection::Current);
+    }
+}
+
+pub fn toggle_fullscreen(
+    kb_input: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<Settings>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if kb_input.just_pressed(KeyCode::F11) {
+        let fullscreen = !settings.fullscreen;
+        set_fullscreen(fullscreen, &mut settings, &mut window_query);
+    }
+}
+
+pub fn set_fullscreen(
+    fullscreen: bool,
+    settings: &mut Settings,
+    window_query: &mut Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if let Ok(mut window) = window_query.single_mut() {
+        settings.fullscreen = fullscreen;
+        window.mode = if fullscreen {
+            WindowMode::BorderlessFullscreen(MonitorSelection::Current)
+        } else {
+            WindowMode::Windowed
+        };
+    }
+}
+
+pub fn sync_theme_setting(current_index: Res<CurrentThemeIndex>, mut settings: ResMut<Settings>) {
+    if !current_index.is_changed() || current_index.is_added() {
+        return;
+    }
+
+    if let Some(theme) = ThemeManifest::builtin().themes.get(current_index.0) {
+        settings.theme = theme.name.clone();
+    }
+}
+
+pub fn sync_character_setting(
+    current_index: Res<CurrentCharacterIndex>,
+    mut settings: ResMut<Settings>,
+) {
+    if !current_index.is_changed() || current_index.is_added() {
+        return;
+    }
+
+    if let Some(character) = CharacterManifest::builtin().characters.get(current_index.0) {
+        settings.character = character.name.clone();
+    }
+}
+
+pub fn sync_volume_setting(volume: Option<Res<AudioVolume>>, mut settings: ResMut<Settings>) {
+    if let Some(volume) = volume
+        && volume.is_changed()
+        && !volume.is_added()
+    {
+        settings.volume = volume.0;
+    }
+}
+
+pub fn sync_game_mode_setting(mode: Res<GameMode>, mut settings: ResMut<Settings>) {
+    if mode.is_changed() && !mode.is_added() {
+        settings.game_mode = *mode;
+    }
+}
+
+pub fn save_settings(settings: Res<Settings>) {
+    if !settings.is_changed() || settings.is_added() {
+        return;
+    }
+
+    match settings.save() {
+        Ok(()) => info!("Saved settings."),
+        Err(err) => warn!("Failed to save settings: {}", err),
+    }
+}
@@ -0,0 +1,353 @@
+use std::f64::consts::TAU;
+
+use bevy::prelude::*;
+use bevy::render::mesh::PrimitiveTopology;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy_rapier2d::prelude::*;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use crate::seed::RunSeed;
+
+/// Half the width of the shaft where it is widest, the walls used to be straight at this distance.
+pub const SHAFT_HALF_WIDTH: f32 = 290.0;
+pub const WALL_CHUNK_HEIGHT: f32 = 2000.0;
+
+// half the width where the shaft is narrowest, without ledges and outcrops
+const MIN_HALF_WIDTH: f32 = 180.0;
+// the walls never drift further out than this, so they stay on screen
+const MAX_EXTENT: f32 = 340.0;
+// the walls are solid up to this far out
+const OUTER_X: f32 = 700.0;
+// the shaft is straight above this and gets its full shape over SHAFT_RAMP below it
+const SHAFT_START: f64 = -500.0;
+const SHAFT_RAMP: f64 = 4000.0;
+// distance between two points of the wall edge
+const EDGE_STEP: f32 = 50.0;
+// every chunk is split into this many slots that can hold one ledge or outcrop each
+const FEATURE_SLOTS: i64 = 2;
+// chance for a slot to hold a ledge or outcrop
+const FEATURE_CHANCE: f64 = 0.5;
+// pixels one repeat of a wall texture covers, the chunk height is a multiple of it
+const WALL_TEXTURE_SIZE: f32 = 250.0;
+
+/// Shape of the shaft for the current run.
+/// All y values are counted from the start of the run, without the world shift.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct Shaft {
+    seed: u64,
+    // (amplitude, wavelength, phase) of the two waves the middle of the shaft drifts along
+    drift: [(f64, f64, f64); 2],
+    // (wavelength, phase) of the wave the shaft narrows and widens along
+    narrowing: (f64, f64),
+}
+
+/// A ledge or outcrop growing out of one wall.
+#[derive(Clone, Copy, Debug)]
+pub struct WallFeature {
+    // -1.0 is the left wall, 1.0 the right one
+    pub side: f32,
+    pub top: f64,
+    // distances from the top to full depth, along full depth and back to the wall
+    pub rise: f32,
+    pub flat: f32,
+    pub fall: f32,
+    // how far it reaches into the shaft
+    pub depth: f32,
+}
+
+impl Default for Shaft {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+pub fn seed_shaft(mut shaft: ResMut<Shaft>, run_seed: Res<RunSeed>) {
+    *shaft = Shaft::new(run_seed.seed);
+}
+
+impl Shaft {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut wave = |amplitude: (f64, f64), wavelength: (f64, f64)| {
+            (
+                rng.gen_range(amplitude.0..=amplitude.1),
+                rng.gen_range(wavelength.0..=wavelength.1),
+                rng.gen_range(0.0..TAU),
+            )
+        };
+        let drift = [
+            wave((60.0, 90.0), (3000.0, 5000.0)),
+            wave((20.0, 40.0), (1200.0, 2000.0)),
+        ];
+
+        Self {
+            seed,
+            drift,
+            narrowing: (rng.gen_range(2500.0..=4000.0), rng.gen_range(0.0..TAU)),
+        }
+    }
+
+    /// Inner edges of the left and right wall at y, without ledges and outcrops.
+    pub fn base_edges(&self, y: f64) -> (f32, f32) {
+        let ramp = ((SHAFT_START - y) / SHAFT_RAMP).clamp(0.0, 1.0);
+
+        let (wavelength, phase) = self.narrowing;
+        let narrowing = 0.5 + 0.5 * (y / wavelength * TAU + phase).sin();
+        let half_width =
+            SHAFT_HALF_WIDTH as f64 - ramp * (SHAFT_HALF_WIDTH - MIN_HALF_WIDTH) as f64 * narrowing;
+
+        let drift: f64 = self
+            .drift
+            .iter()
+            .map(|(amplitude, wavelength, phase)| amplitude * (y / wavelength * TAU + phase).sin())
+            .sum();
+        // wide parts of the shaft can not drift as far
+        let max_drift = MAX_EXTENT as f64 - half_width;
+        let center = (ramp * drift).clamp(-max_drift, max_drift);
+
+        ((center - half_width) as f32, (center + half_width) as f32)
+    }
+
+    /// Inner edges of the left and right wall at y, including ledges and outcrops.
+    pub fn edges(&self, y: f64) -> (f32, f32) {
+        let (mut left, mut right) = self.base_edges(y);
+        for feature in self.features(chunk_at(y)) {
+            if feature.side < 0.0 {
+                left += feature.offset_at(y);
+            } else {
+                right -= feature.offset_at(y);
+            }
+        }
+        (left, right)
+    }
+
+    /// Free space between the walls anywhere from `y_low` to `y_high`.
+    pub fn free_range(&self, y_low: f64, y_high: f64) -> (f32, f32) {
+        let steps = ((y_high - y_low) / EDGE_STEP as f64).ceil().max(1.0) as usize;
+        (0..=steps)
+            .map(|step| self.edges(y_low + (y_high - y_low) * step as f64 / steps as f64))
+            .fold(
+                (f32::MIN, f32::MAX),
+                |(left, right), (edge_left, edge_right)| {
+                    (left.max(edge_left), right.min(edge_right))
+                },
+            )
+    }
+
+    /// Ledges and outcrops of a chunk. They never reach out of it.
+    pub fn features(&self, chunk: i64) -> Vec<WallFeature> {
+        // every chunk has its own rng, so the order chunks are streamed in does not matter
+        let mut rng =
+            StdRng::seed_from_u64(self.seed ^ (chunk as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        let slot_height = (WALL_CHUNK_HEIGHT / FEATURE_SLOTS as f32) as f64;
+        let chunk_top = chunk_center(chunk) + WALL_CHUNK_HEIGHT as f64 / 2.0;
+
+        let mut features = Vec::new();
+        for slot in 0..FEATURE_SLOTS {
+            let slot_top = chunk_top - slot as f64 * slot_height;
+            let ramp = ((SHAFT_START - slot_top) / SHAFT_RAMP).clamp(0.0, 1.0) as f32;
+            let side = if rng.gen_bool(0.5) { -1.0 } else { 1.0 };
+            let ledge = rng.gen_bool(0.4);
+            let mut feature = if ledge {
+                WallFeature {
+                    side,
+                    top: 0.0,
+                    rise: 2.0,
+                    flat: rng.gen_range(20.0..=30.0),
+                    fall: 20.0,
+                    depth: rng.gen_range(60.0..=120.0),
+                }
+            } else {
+                WallFeature {
+                    side,
+                    top: 0.0,
+                    rise: rng.gen_range(100.0..=200.0),
+                    flat: rng.gen_range(0.0..=120.0),
+                    fall: rng.gen_range(100.0..=200.0),
+                    depth: rng.gen_range(30.0..=80.0),
+                }
+            };
+            let free = slot_height - feature.length() as f64;
+            feature.top = slot_top - rng.gen_range(0.0..free);
+            feature.depth *= ramp;
+
+            if ramp > 0.0 && rng.gen_bool(FEATURE_CHANCE) {
+                features.push(feature);
+            }
+        }
+        features
+    }
+
+    /// Mesh and collider of one wall of a chunk, relative to the middle of the chunk.
+    /// `side` is -1.0 for the left wall and 1.0 for the right one.
+    pub fn wall_chunk(&self, chunk: i64, side: f32) -> (Mesh, Collider) {
+        let center = chunk_center(chunk);
+        let half_height = WALL_CHUNK_HEIGHT / 2.0;
+        let features: Vec<_> = self
+            .features(chunk)
+            .into_iter()
+            .filter(|feature| feature.side == side)
+            .collect();
+
+        // the corners of ledges and outcrops are added, so they are not cut off between steps
+        let mut heights: Vec<f32> = (0..=(WALL_CHUNK_HEIGHT / EDGE_STEP) as i32)
+            .map(|step| half_height - step as f32 * EDGE_STEP)
+            .collect();
+        for feature in &features {
+            let top = (feature.top - center) as f32;
+            heights.extend([
+                top,
+                top - feature.rise,
+                top - feature.rise - feature.flat,
+                top - feature.length(),
+            ]);
+        }
+        heights.sort_by(|a, b| b.total_cmp(a));
+        heights.dedup_by(|a, b| (*a - *b).abs() < 0.5);
+
+        let edge: Vec<Vec2> = heights
+            .iter()
+            .map(|height| {
+                let y = center + *height as f64;
+                let (left, right) = self.base_edges(y);
+                let offset: f32 = features.iter().map(|feature| feature.offset_at(y)).sum();
+                let x = if side < 0.0 {
+                    left + offset
+                } else {
+                    right - offset
+                };
+                Vec2::new(x, *height)
+            })
+            .collect();
+
+        let mut positions = Vec::new();
+        let mut parts = Vec::new();
+        for pair in edge.windows(2) {
+            let (top, bottom) = (pair[0], pair[1]);
+            let quad = [
+                top,
+                bottom,
+                Vec2::new(OUTER_X * side, bottom.y),
+                Vec2::new(OUTER_X * side, top.y),
+            ];
+            for index in [0, 1, 2, 0, 2, 3] {
+                positions.push(quad[index]);
+            }
+            if let Some(part) = Collider::convex_hull(&quad) {
+                parts.push((Vec2::ZERO, 0.0, part));
+            }
+        }
+
+        let uvs: Vec<[f32; 2]> = positions
+            .iter()
+            .map(|position| {
+                [
+                    position.x / WALL_TEXTURE_SIZE,
+                    -position.y / WALL_TEXTURE_SIZE,
+                ]
+            })
+            .collect();
+        let positions: Vec<[f32; 3]> = positions
+            .iter()
+            .map(|position| [position.x, position.y, 0.0])
+            .collect();
+
+        let mesh = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+
+        (mesh, Collider::compound(parts))
+    }
+}
+
+impl WallFeature {
+    pub fn length(&self) -> f32 {
+        self.rise + self.flat + self.fall
+    }
+
+    /// How far the feature reaches into the shaft at y.
+    pub fn offset_at(&self, y: f64) -> f32 {
+        let below = (self.top - y) as f32;
+        if below < 0.0 || below > self.length() {
+            0.0
+        } else if below < self.rise {
+            self.depth * below / self.rise
+        } else if below < self.rise + self.flat {
+            self.depth
+        } else {
+            self.depth * (self.length() - below) / self.fall
+        }
+    }
+}
+
+/// Middle of the chunk, counted from the start of the run.
+pub fn chunk_center(chunk: i64) -> f64 {
+    chunk as f64 * WALL_CHUNK_HEIGHT as f64
+}
+
+pub fn chunk_at(y: f64) -> i64 {
+    (y / WALL_CHUNK_HEIGHT as f64).round() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEEDS: std::ops::Range<u64> = 0..20;
+
+    // from above the start down past a few narrowing waves
+    fn heights() -> impl Iterator<Item = f64> {
+        (0..400).map(|step| 1000.0 - step as f64 * 137.0)
+    }
+
+    #[test]
+    fn free_range_is_never_inverted() {
+        for seed in SEEDS {
+            let shaft = Shaft::new(seed);
+            for y in heights() {
+                let (left, right) = shaft.free_range(y - 150.0, y + 150.0);
+                assert!(left < right, "seed {seed} at {y}: {left} > {right}");
+            }
+        }
+    }
+
+    #[test]
+    fn edges_are_never_inverted() {
+        for seed in SEEDS {
+            let shaft = Shaft::new(seed);
+            for y in heights() {
+                let (base_left, base_right) = shaft.base_edges(y);
+                let (left, right) = shaft.edges(y);
+                assert!(base_left < base_right, "seed {seed} at {y}");
+                assert!(left < right, "seed {seed} at {y}: {left} > {right}");
+                // ledges and outcrops only ever narrow the shaft
+                assert!(
+                    left >= base_left && right <= base_right,
+                    "seed {seed} at {y}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn features_stay_inside_their_chunk() {
+        for seed in SEEDS {
+            let shaft = Shaft::new(seed);
+            for chunk in -30..=1 {
+                let top = chunk_center(chunk) + WALL_CHUNK_HEIGHT as f64 / 2.0;
+                let bottom = top - WALL_CHUNK_HEIGHT as f64;
+                for feature in shaft.features(chunk) {
+                    assert!(feature.top <= top, "seed {seed} chunk {chunk}");
+                    assert!(
+                        feature.top - feature.length() as f64 >= bottom,
+                        "seed {seed} chunk {chunk}"
+                    );
+                    assert!(feature.depth >= 0.0, "seed {seed} chunk {chunk}");
+                }
+            }
+        }
+    }
+}
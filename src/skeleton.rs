@@ -0,0 +1,207 @@
+use std::collections::HashSet;
+
+use bevy::asset::{Asset, LoadState};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::reflect::Reflect;
+use bevy_common_assets::ron::RonAssetPlugin;
+use serde::{Deserialize, Serialize};
+
+use crate::obstacles::ObstacleShape;
+
+/// Body parts and joints of a ragdoll character.
+#[derive(Asset, Serialize, Deserialize, Clone, Debug, Reflect)]
+pub struct Skeleton {
+    pub name: String,
+    // multiplies every size, position and anchor
+    #[serde(default = "default_one")]
+    pub scale: f32,
+    #[serde(default = "default_one")]
+    pub gravity_scale: f32,
+    pub parts: Vec<SkeletonPart>,
+    pub joints: Vec<SkeletonJoint>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Reflect)]
+pub struct SkeletonPart {
+    // entities are named "player_" followed by this. "torso" is steered, "head" uses the head color of the theme.
+    pub name: String,
+    pub shape: ObstacleShape,
+    // circles use the width as the diameter
+    pub size: (f32, f32),
+    // offset from the torso when the run starts
+    #[serde(default)]
+    pub position: (f32, f32),
+    // worked out from the size if not set
+    #[serde(default)]
+    pub mass: Option<f32>,
+    // contact force in millions that breaks the part
+    pub damage_threshold: f32,
+    // breaking this part ends the run at once
+    #[serde(default)]
+    pub fatal: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Reflect)]
+pub struct SkeletonJoint {
+    pub part: String,
+    // the part it is attached to
+    pub parent: String,
+    // relative to the middle of the part and of the parent
+    pub anchor: (f32, f32),
+    pub parent_anchor: (f32, f32),
+    // lowest and highest angle in radians, turns freely if not set
+    #[serde(default)]
+    pub limits: Option<(f32, f32)>,
+}
+
+#[derive(Deserialize, Debug, Clone, Serialize, Reflect)]
+pub struct CharacterManifestEntry {
+    pub path: String,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Reflect)]
+pub struct CharacterManifest {
+    pub characters: Vec<CharacterManifestEntry>,
+}
+
+/// Index of the selected character in the character manifest.
+#[derive(Resource, Default)]
+pub struct CurrentCharacterIndex(pub usize);
+
+#[derive(Resource, Default)]
+pub struct SkeletonHandle(pub Handle<Skeleton>);
+
+// used until the skeleton of the selected character is loaded, or if it is broken
+#[derive(Resource)]
+pub struct BuiltinSkeleton(pub Skeleton);
+
+/// The skeleton of the selected character, or the built-in one while it is loading.
+#[derive(SystemParam)]
+pub struct Skeletons<'w> {
+    handle: Res<'w, SkeletonHandle>,
+    skeletons: Res<'w, Assets<Skeleton>>,
+    builtin: Res<'w, BuiltinSkeleton>,
+}
+
+pub struct SkeletonPlugin {
+    // name of the character to start with, the first character of the manifest is used if not set
+    pub character: Option<String>,
+}
+
+impl Plugin for SkeletonPlugin {
+    fn build(&self, app: &mut App) {
+        let manifest = CharacterManifest::builtin();
+        let index = match &self.character {
+            Some(name) => manifest.find(name).unwrap_or_else(|| {
+                warn!("Character '{}' not found, using default character.", name);
+                0
+            }),
+            None => 0,
+        };
+
+        app.add_plugins(RonAssetPlugin::<Skeleton>::new(&["skeleton.ron"]))
+            .insert_resource(BuiltinSkeleton(Skeleton::builtin()))
+            .insert_resource(CurrentCharacterIndex(index))
+            .add_systems(PreStartup, load_skeleton)
+            .add_systems(Update, switch_character);
+    }
+}
+
+impl CharacterManifest {
+    /// Manifest compiled into the binary, available before any asset is loaded.
+    pub fn builtin() -> Self {
+        ron::from_str(include_str!("../assets/skeletons/manifest.ron"))
+            .expect("built-in character manifest is valid")
+    }
+
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.characters
+            .iter()
+            .position(|character| character.name.eq_ignore_ascii_case(name))
+    }
+}
+
+impl Skeleton {
+    /// Skeleton compiled into the binary, available before any asset is loaded.
+    pub fn builtin() -> Self {
+        ron::from_str(include_str!("../assets/skeletons/human.skeleton.ron"))
+            .expect("built-in skeleton is valid")
+    }
+
+    /// Checks that there is a torso and that every joint connects two parts.
+    pub fn validate(&self) -> Result<(), String> {
+        let mut names = HashSet::new();
+        for part in &self.parts {
+            if !names.insert(part.name.as_str()) {
+                return Err(format!("part '{}' is defined twice", part.name));
+            }
+        }
+        if !names.contains("torso") {
+            return Err("there is no part named 'torso'".to_string());
+        }
+        for joint in &self.joints {
+            for name in [&joint.part, &joint.parent] {
+                if !names.contains(name.as_str()) {
+                    return Err(format!("joint uses unknown part '{}'", name));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Skeletons<'_> {
+    pub fn get(&self) -> &Skeleton {
+        match self.skeletons.get(&self.handle.0) {
+            Some(skeleton) => match skeleton.validate() {
+                Ok(()) => skeleton,
+                Err(err) => {
+                    warn!("Skeleton '{}' is broken: {}.", skeleton.name, err);
+                    &self.builtin.0
+                }
+            },
+            None => &self.builtin.0,
+        }
+    }
+}
+
+pub fn load_skeleton(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    current_index: Res<CurrentCharacterIndex>,
+) {
+    let manifest = CharacterManifest::builtin();
+    let path = manifest
+        .characters
+        .get(current_index.0)
+        .map_or("skeletons/human.skeleton.ron", |character| {
+            character.path.as_str()
+        });
+
+    commands.insert_resource(SkeletonHandle(asset_server.load(path)));
+}
+
+// loads the skeleton whenever the current index is changed from a menu, the next run uses it
+pub fn switch_character(
+    commands: Commands,
+    asset_server: Res<AssetServer>,
+    current_index: Res<CurrentCharacterIndex>,
+) {
+    if current_index.is_changed() && !current_index.is_added() {
+        load_skeleton(commands, asset_server, current_index);
+    }
+}
+
+/// True once the skeleton of the selected character finished loading, or failed to.
+pub fn skeleton_loaded(handle: Res<SkeletonHandle>, asset_server: Res<AssetServer>) -> bool {
+    matches!(
+        asset_server.load_state(&handle.0),
+        LoadState::Loaded | LoadState::Failed(_)
+    )
+}
+
+fn default_one() -> f32 {
+    1.0
+}
@@ -1,9 +1,22 @@
+use std::collections::HashMap;
+
 use bevy::asset::Asset;
+use bevy::image::{ImageAddressMode, ImageLoaderSettings, ImageSampler, ImageSamplerDescriptor};
 use bevy::prelude::*;
 use bevy::reflect::Reflect;
+use bevy_common_assets::ron::RonAssetPlugin;
 use serde::{Deserialize, Serialize};
 
-use crate::game_states::GameState;
+use crate::{
+    actions::{Action, ActionInput},
+    environment::Wall,
+    ghost::GhostPart,
+    obstacles::{ObstacleLook, ObstacleShape},
+    pickups::{Pickup, PickupKind},
+    player_setup::PlayerBodyPart,
+    shaft::chunk_center,
+    zones::{DepthZones, ZoneBlend, depth_at_run_y},
+};
 
 #[derive(Resource, Default)]
 pub struct JustLoadedTheme(pub bool);
@@ -19,6 +32,39 @@ pub struct ThemeInfo {
 #[derive(Resource, Default)]
 pub struct ThemeManifestHandle(pub Handle<ThemeManifest>);
 
+pub struct ThemePlugin {
+    // name of the theme to start with, the first theme of the manifest is used if not set
+    pub theme: Option<String>,
+}
+
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut App) {
+        let manifest = ThemeManifest::builtin();
+        let index = match &self.theme {
+            Some(name) => manifest.find(name).unwrap_or_else(|| {
+                warn!("Theme '{}' not found, using default theme.", name);
+                0
+            }),
+            None => 0,
+        };
+
+        app.add_plugins((
+            RonAssetPlugin::<Theme>::new(&["theme.ron"]),
+            RonAssetPlugin::<ThemeManifest>::new(&[".ron"]),
+        ))
+        .insert_resource(ThemeInfo { loaded: false })
+        .insert_resource(CurrentThemeIndex(index))
+        // starts the music of the initial theme
+        .insert_resource(JustLoadedTheme(true))
+        .add_systems(PreStartup, load_themes_manifest)
+        .add_systems(PreStartup, load_theme)
+        .add_systems(PostStartup, show_current_theme)
+        .add_systems(PreUpdate, check_theme)
+        .add_systems(Update, (cycle_theme, switch_theme).chain())
+        .add_systems(Update, update_theme);
+    }
+}
+
 #[derive(Deserialize, Debug, Clone, Serialize, Reflect)]
 pub struct ThemeManifestEntry {
     pub path: String,
@@ -30,6 +76,20 @@ pub struct ThemeManifest {
     pub themes: Vec<ThemeManifestEntry>,
 }
 
+impl ThemeManifest {
+    /// Manifest compiled into the binary, available before any asset is loaded.
+    pub fn builtin() -> Self {
+        ron::from_str(include_str!("../assets/themes/manifest.ron"))
+            .expect("built-in theme manifest is valid")
+    }
+
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.themes
+            .iter()
+            .position(|theme| theme.name.eq_ignore_ascii_case(name))
+    }
+}
+
 #[derive(Asset, Serialize, Deserialize, Clone, Debug, Reflect)]
 pub struct Theme {
     // color for background
@@ -53,6 +113,42 @@ pub struct Theme {
     // base color for obstacles. randomness is added afterwards.
     #[serde(default = "default_obstacle_color")]
     pub obstacles_base_color: ColorData,
+    // image textures for obstacles by shape, one is picked at random. shapes without textures use the colors above.
+    #[serde(default)]
+    pub obstacle_textures: HashMap<ObstacleShape, Vec<String>>,
+    // tints obstacle textures randomly using obstacles_color_variation if true
+    #[serde(default)]
+    pub obstacles_texture_tint: bool,
+    // color of spikes that break a body part at once
+    #[serde(default = "default_spikes_color")]
+    pub spikes_color: ColorData,
+    // color of pads that bounce the player off
+    #[serde(default = "default_bouncy_color")]
+    pub bouncy_color: ColorData,
+    // color of surfaces that hold body parts in place
+    #[serde(default = "default_sticky_color")]
+    pub sticky_color: ColorData,
+    // color of zones that slow the player down. should be see-through.
+    #[serde(default = "default_slow_zone_color")]
+    pub slow_zone_color: ColorData,
+    // color of coins that add to the score
+    #[serde(default = "default_coin_color")]
+    pub coin_color: ColorData,
+    // color of shields that absorb the next hit
+    #[serde(default = "default_shield_color")]
+    pub shield_color: ColorData,
+    // color of splints that heal a broken body part
+    #[serde(default = "default_splint_color")]
+    pub splint_color: ColorData,
+    // color of pickups that slow the game down
+    #[serde(default = "default_slow_motion_color")]
+    pub slow_motion_color: ColorData,
+    // color of magnets that pull coins in
+    #[serde(default = "default_magnet_color")]
+    pub magnet_color: ColorData,
+    // image textures for pickups by kind. kinds without textures use the colors above.
+    #[serde(default)]
+    pub pickup_textures: HashMap<PickupKind, String>,
     // color when body part is broken (default is yellow)
     #[serde(default = "default_broken_color")]
     pub player_broken_color: ColorData,
@@ -65,6 +161,9 @@ pub struct Theme {
     // color for the walls that are to the left and right of the player
     #[serde(default = "default_walls_color")]
     pub walls_color: ColorData,
+    // path to image texture repeated along the walls. walls_color is used if empty.
+    #[serde(default = "default_empty")]
+    pub walls_texture: String,
     // path to background music to play
     #[serde(default = "default_empty")]
     pub music_path: String,
@@ -94,11 +193,49 @@ impl ColorData {
     }
 }
 
+impl Theme {
+    /// Material of the walls in a depth zone. Zones can replace the color and texture of the theme.
+    pub fn walls_material(&self, asset_server: &AssetServer, zone: &ZoneBlend) -> ColorMaterial {
+        let texture = zone
+            .nearest()
+            .and_then(|zone| zone.walls_texture.as_ref())
+            .unwrap_or(&self.walls_texture);
+        if texture.is_empty() {
+            return ColorMaterial::from(
+                zone.color(self.walls_color.to_color(), |zone| &zone.walls_color),
+            );
+        }
+
+        // the wall meshes are larger than the texture, it has to repeat
+        let texture = asset_server.load_with_settings(
+            format!("themes/{}", texture),
+            |settings: &mut ImageLoaderSettings| {
+                settings.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
+                    address_mode_u: ImageAddressMode::Repeat,
+                    address_mode_v: ImageAddressMode::Repeat,
+                    ..default()
+                });
+            },
+        );
+        ColorMaterial::from(texture)
+    }
+}
+
 #[derive(Resource, Default)]
 pub struct ThemeHandle(pub Handle<Theme>);
 
-pub fn load_theme(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let theme_handle = ThemeHandle(asset_server.load("themes/default.theme.ron"));
+pub fn load_theme(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    current_index: Res<CurrentThemeIndex>,
+) {
+    let manifest = ThemeManifest::builtin();
+    let path = manifest
+        .themes
+        .get(current_index.0)
+        .map_or("themes/default.theme.ron", |theme| theme.path.as_str());
+
+    let theme_handle = ThemeHandle(asset_server.load(path));
     commands.insert_resource(theme_handle);
 }
 
@@ -108,34 +245,49 @@ pub fn load_themes_manifest(mut commands: Commands, asset_server: Res<AssetServe
 }
 
 pub fn cycle_theme(
-    kb_input: Res<ButtonInput<KeyCode>>,
+    action_input: Res<ActionInput>,
     manifest_handle: Res<ThemeManifestHandle>,
     manifests: Res<Assets<ThemeManifest>>,
-    mut theme_handle: ResMut<ThemeHandle>,
     mut current_index: ResMut<CurrentThemeIndex>,
+) {
+    if action_input.just_pressed(Action::NextTheme)
+        && let Some(manifest) = manifests.get(&manifest_handle.0)
+        && !manifest.themes.is_empty()
+    {
+        current_index.0 = (current_index.0 + 1) % manifest.themes.len();
+    }
+}
+
+// loads the theme whenever the current index is changed, by cycling or from a menu
+pub fn switch_theme(
+    current_index: Res<CurrentThemeIndex>,
+    mut theme_handle: ResMut<ThemeHandle>,
     asset_server: Res<AssetServer>,
     mut text_query: Query<&mut Text>,
     mut just_loaded: ResMut<JustLoadedTheme>,
 ) {
-    if kb_input.just_pressed(KeyCode::Tab) {
-        if let Some(manifest) = manifests.get(&manifest_handle.0) {
-            let themes = &manifest.themes;
-            if !themes.is_empty() {
-                current_index.0 = (current_index.0 + 1) % themes.len();
-                let next_theme_path = &themes[current_index.0].path;
-                theme_handle.0 = asset_server.load(next_theme_path);
-                info!("Switched to theme: {}", &themes[current_index.0].name);
-
-                for mut text_item in text_query.iter_mut() {
-                    if text_item.0.contains("Current Theme") {
-                        text_item.0 = format!("Current Theme: {}", &themes[current_index.0].name);
-                    }
-                }
-            }
-        }
+    if !current_index.is_changed() || current_index.is_added() {
+        return;
+    }
 
-        just_loaded.0 = true;
+    let Some(theme) = ThemeManifest::builtin()
+        .themes
+        .get(current_index.0)
+        .cloned()
+    else {
+        return;
+    };
+
+    theme_handle.0 = asset_server.load(&theme.path);
+    info!("Switched to theme: {}", &theme.name);
+
+    for mut text_item in text_query.iter_mut() {
+        if text_item.0.contains("Current Theme") {
+            text_item.0 = format!("Current Theme: {}", &theme.name);
+        }
     }
+
+    just_loaded.0 = true;
 }
 
 pub fn update_theme(
@@ -144,16 +296,29 @@ pub fn update_theme(
     themes: Res<Assets<Theme>>,
     mut camera_query: Query<&mut Camera>,
     mut text_color_query: Query<&mut TextColor>,
-    mut mesh_query: Query<(&Name, &mut MeshMaterial2d<ColorMaterial>)>,
-    mut image_node_query: Query<&mut ImageNode>,
-    game_state: Res<State<GameState>>,
+    // the ghost keeps its own translucent material
+    mut mesh_query: Query<
+        (&Name, &mut MeshMaterial2d<ColorMaterial>),
+        (
+            Without<ObstacleLook>,
+            Without<Pickup>,
+            Without<Wall>,
+            Without<GhostPart>,
+        ),
+    >,
+    mut wall_query: Query<
+        (&Wall, &mut MeshMaterial2d<ColorMaterial>),
+        (Without<ObstacleLook>, Without<Pickup>),
+    >,
+    mut obstacle_query: Query<(&ObstacleLook, &mut MeshMaterial2d<ColorMaterial>)>,
+    mut pickup_query: Query<(&Pickup, &mut MeshMaterial2d<ColorMaterial>), Without<ObstacleLook>>,
+    // body parts are only spawned for a new run, so resuming keeps broken parts colored
+    new_parts: Query<(), Added<PlayerBodyPart>>,
     asset_server: Res<AssetServer>,
     theme_info: Res<ThemeInfo>,
+    zones: DepthZones,
 ) {
-    if !theme_handle.is_changed()
-        && !(game_state.is_changed() && *game_state.get() == GameState::InGame)
-        && !theme_info.is_changed()
-    {
+    if !theme_handle.is_changed() && new_parts.is_empty() && !theme_info.is_changed() {
         return;
     }
 
@@ -170,12 +335,7 @@ pub fn update_theme(
             text_color.0 = theme.text_color.to_color();
         }
 
-        // hints for keybindings
-        for mut image_node in image_node_query.iter_mut() {
-            image_node.color = theme.text_color.to_color();
-        }
-
-        // player body part color and walls color
+        // player body part color and rod color
         for (part_name, mut mesh_material) in mesh_query.iter_mut() {
             if part_name.as_str() == "player_head" {
                 if !theme.player_head_texture.is_empty() {
@@ -186,12 +346,26 @@ pub fn update_theme(
                 }
             } else if part_name.as_str().contains("player") {
                 mesh_material.0 = materials.add(theme.player_body_color.to_color());
-            } else if part_name.as_str() == "wall" {
+            } else if part_name.as_str() == "rod" {
                 mesh_material.0 = materials.add(theme.walls_color.to_color());
-            } else if part_name.as_str().contains("obstacle") {
-                mesh_material.0 = materials.add(theme.obstacles_base_color.to_color())
             }
         }
+
+        // walls look like the zone each chunk is in
+        for (wall, mut mesh_material) in wall_query.iter_mut() {
+            let zone = zones
+                .get()
+                .blend_at(depth_at_run_y(chunk_center(wall.chunk)));
+            mesh_material.0 = materials.add(theme.walls_material(&asset_server, &zone));
+        }
+
+        // obstacles keep their random color or texture
+        for (look, mut mesh_material) in obstacle_query.iter_mut() {
+            mesh_material.0 = materials.add(look.material(Some(theme), &asset_server));
+        }
+        for (pickup, mut mesh_material) in pickup_query.iter_mut() {
+            mesh_material.0 = materials.add(pickup.0.material(Some(theme), &asset_server));
+        }
     } else {
         warn!("Failed to load theme. Trying again...")
     }
@@ -249,6 +423,87 @@ fn default_obstacle_color() -> ColorData {
     }
 }
 
+fn default_spikes_color() -> ColorData {
+    ColorData {
+        red: 0.75,
+        green: 0.2,
+        blue: 0.2,
+        alpha: 1.0,
+    }
+}
+
+fn default_bouncy_color() -> ColorData {
+    ColorData {
+        red: 0.2,
+        green: 0.8,
+        blue: 0.4,
+        alpha: 1.0,
+    }
+}
+
+fn default_sticky_color() -> ColorData {
+    ColorData {
+        red: 0.8,
+        green: 0.6,
+        blue: 0.1,
+        alpha: 1.0,
+    }
+}
+
+fn default_slow_zone_color() -> ColorData {
+    ColorData {
+        red: 0.3,
+        green: 0.5,
+        blue: 1.0,
+        alpha: 0.3,
+    }
+}
+
+fn default_coin_color() -> ColorData {
+    ColorData {
+        red: 1.0,
+        green: 0.85,
+        blue: 0.2,
+        alpha: 1.0,
+    }
+}
+
+fn default_shield_color() -> ColorData {
+    ColorData {
+        red: 0.3,
+        green: 0.8,
+        blue: 1.0,
+        alpha: 1.0,
+    }
+}
+
+fn default_splint_color() -> ColorData {
+    ColorData {
+        red: 0.9,
+        green: 0.75,
+        blue: 0.55,
+        alpha: 1.0,
+    }
+}
+
+fn default_slow_motion_color() -> ColorData {
+    ColorData {
+        red: 0.7,
+        green: 0.4,
+        blue: 1.0,
+        alpha: 1.0,
+    }
+}
+
+fn default_magnet_color() -> ColorData {
+    ColorData {
+        red: 0.9,
+        green: 0.2,
+        blue: 0.5,
+        alpha: 1.0,
+    }
+}
+
 fn default_broken_color() -> ColorData {
     ColorData {
         red: 1.0,
@@ -280,9 +535,15 @@ fn default_walls_color() -> ColorData {
     }
 }
 
-pub fn show_current_theme(mut commands: Commands) {
+pub fn show_current_theme(mut commands: Commands, current_index: Res<CurrentThemeIndex>) {
+    let manifest = ThemeManifest::builtin();
+    let name = manifest
+        .themes
+        .get(current_index.0)
+        .map_or("Default", |theme| theme.name.as_str());
+
     commands.spawn((
-        Text::new("Current Theme: Default"),
+        Text::new(format!("Current Theme: {}", name)),
         TextFont {
             font_size: 18.0,
             ..default()
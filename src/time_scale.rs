@@ -0,0 +1,37 @@
+use bevy::prelude::*;
+
+/// Factors the speed of the game is scaled by, multiplied together once per frame.
+/// Every feature that changes the speed sets its own factor, so they do not overwrite each other.
+#[derive(Resource)]
+pub struct TimeScale {
+    // replay fast-forward
+    pub fast_forward: f32,
+    // slow motion power-up
+    pub slow_motion: f32,
+}
+
+impl Default for TimeScale {
+    fn default() -> Self {
+        Self {
+            fast_forward: 1.0,
+            slow_motion: 1.0,
+        }
+    }
+}
+
+pub struct TimeScalePlugin;
+
+impl Plugin for TimeScalePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TimeScale>()
+            .add_systems(PostUpdate, apply_time_scale);
+    }
+}
+
+pub fn apply_time_scale(time_scale: Res<TimeScale>, mut time: ResMut<Time<Virtual>>) {
+    // physics runs in the fixed ticks, so it simply runs more or fewer ticks per frame
+    let speed = time_scale.fast_forward * time_scale.slow_motion;
+    if time.relative_speed() != speed {
+        time.set_relative_speed(speed);
+    }
+}
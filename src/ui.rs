@@ -1,4 +1,8 @@
+use crate::actions::{Action, button_name, key_name};
+use crate::game_modes::{GameMode, RunStart, TIME_ATTACK_LIMIT_S};
+use crate::game_states::GameState;
 use crate::player::PlayerData;
+use crate::settings::Settings;
 use bevy::prelude::*;
 
 const WHITE_COLOR: Color = Color::srgb(1.0, 1.0, 1.0);
@@ -6,6 +10,21 @@ const WHITE_COLOR: Color = Color::srgb(1.0, 1.0, 1.0);
 #[derive(Component)]
 pub struct ScoreText;
 
+// hints for the current key bindings
+#[derive(Component)]
+pub struct KeybindingsText;
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostStartup, spawn_score_ui)
+            .add_systems(PostStartup, show_keybindings)
+            .add_systems(Update, update_keybindings)
+            .add_systems(PostUpdate, update_score_ui);
+    }
+}
+
 pub fn spawn_score_ui(mut commands: Commands, player_data: ResMut<PlayerData>) {
     commands.spawn((
         Text::new(format!("Score: {}", player_data.score)),
@@ -31,126 +50,77 @@ pub fn spawn_score_ui(mut commands: Commands, player_data: ResMut<PlayerData>) {
 pub fn update_score_ui(
     mut score_query: Query<&mut Text, With<ScoreText>>,
     player_data: Res<PlayerData>,
+    mode: Res<GameMode>,
+    run_start: Res<RunStart>,
+    time: Res<Time>,
+    game_state: Res<State<GameState>>,
 ) {
     if let Ok(mut score_text) = score_query.single_mut() {
-        score_text.0 = format!("Score: {}", player_data.score);
+        let in_run = matches!(game_state.get(), GameState::InGame | GameState::Paused);
+
+        score_text.0 = if *mode == GameMode::TimeAttack && in_run {
+            let time_left = (TIME_ATTACK_LIMIT_S - (time.elapsed_secs() - run_start.0)).max(0.0);
+            format!(
+                "Score: {}  Time: {:.0}",
+                player_data.score,
+                time_left.ceil()
+            )
+        } else {
+            format!("Score: {}", player_data.score)
+        };
     }
 }
 
-pub fn show_keybindings(mut commands: Commands, asset_server: Res<AssetServer>) {
-    // left arrow
+pub fn show_keybindings(mut commands: Commands) {
     commands.spawn((
-        ImageNode {
-            image: asset_server.load("controls/arrow-left.png"),
-            color: Color::srgb(1.0, 1.0, 1.0),
-            ..Default::default()
-        },
-        Node {
-            left: Val::Px(10.0),
-            top: Val::Px(10.0),
-            width: Val::Px(22.0),
-            height: Val::Px(22.0),
-            ..default()
-        },
-    ));
-    commands.spawn((
-        Text::new(": Move Left"),
+        Text::new(""),
         TextFont {
             font_size: 18.0,
             ..default()
         },
         TextColor(WHITE_COLOR),
         Node {
-            left: Val::Px(37.0),
-            top: Val::Px(11.0),
-            ..default()
-        },
-    ));
-
-    // right arrow
-    commands.spawn((
-        ImageNode {
-            image: asset_server.load("controls/arrow-right.png"),
-            color: Color::srgb(1.0, 1.0, 1.0),
-            ..default()
-        },
-        Node {
+            position_type: PositionType::Absolute,
             left: Val::Px(10.0),
-            top: Val::Px(40.0),
-            width: Val::Px(22.0),
-            height: Val::Px(22.0),
-            ..default()
-        },
-    ));
-    commands.spawn((
-        Text::new(": Move Right"),
-        TextFont {
-            font_size: 18.0,
-            ..Default::default()
-        },
-        TextColor(WHITE_COLOR),
-        Node {
-            left: Val::Px(37.0),
-            top: Val::Px(41.0),
+            top: Val::Px(10.0),
             ..default()
         },
+        KeybindingsText,
     ));
+}
 
-    // r key
-    commands.spawn((
-        ImageNode {
-            image: asset_server.load("controls/r.png"),
-            color: Color::srgb(1.0, 1.0, 1.0),
-            ..default()
-        },
-        Node {
-            left: Val::Px(10.0),
-            top: Val::Px(70.0),
-            width: Val::Px(22.0),
-            height: Val::Px(22.0),
-            ..default()
-        },
-    ));
-    commands.spawn((
-        Text::new(": Reset"),
-        TextFont {
-            font_size: 18.0,
-            ..default()
-        },
-        TextColor(WHITE_COLOR),
-        Node {
-            left: Val::Px(37.0),
-            top: Val::Px(71.0),
-            ..default()
-        },
-    ));
+pub fn update_keybindings(
+    settings: Res<Settings>,
+    gamepads: Query<(), With<Gamepad>>,
+    mut gamepad_connected: Local<bool>,
+    mut text_query: Query<&mut Text, With<KeybindingsText>>,
+) {
+    // gamepad buttons are only shown while a gamepad is connected
+    let connected = !gamepads.is_empty();
+    if !settings.is_changed() && connected == *gamepad_connected {
+        return;
+    }
+    *gamepad_connected = connected;
 
-    // tab key
-    commands.spawn((
-        ImageNode {
-            image: asset_server.load("controls/tab.png"),
-            color: Color::srgb(1.0, 1.0, 1.0),
-            ..default()
-        },
-        Node {
-            left: Val::Px(10.0),
-            top: Val::Px(100.0),
-            width: Val::Px(35.0),
-            height: Val::Px(22.0),
-            ..default()
-        },
-    ));
-    commands.spawn((
-        Text::new(": Next Theme"),
-        TextFont {
-            font_size: 18.0,
-            ..default()
-        },
-        TextColor(WHITE_COLOR),
-        Node {
-            left: Val::Px(50.0),
-            top: Val::Px(101.0),
-            ..default()
-        },
-    ));
+    if let Ok(mut text) = text_query.single_mut() {
+        text.0 = [
+            Action::MoveLeft,
+            Action::MoveRight,
+            Action::Reset,
+            Action::Retry,
+            Action::NextTheme,
+        ]
+        .iter()
+        .map(|action| {
+            let key = key_name(settings.key_bindings.key(*action));
+            if connected {
+                let button = button_name(settings.gamepad_bindings.button(*action));
+                format!("{} / {}: {}", key, button, action.name())
+            } else {
+                format!("{}: {}", key, action.name())
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    }
 }
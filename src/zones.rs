@@ -0,0 +1,328 @@
+use std::collections::HashMap;
+
+use bevy::asset::Asset;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::reflect::Reflect;
+use bevy_common_assets::ron::RonAssetPlugin;
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game_states::{GameState, NewRun},
+    player::{WorldShift, recenter_world},
+    player_setup::{PLAYER_START_Y, PlayerTorso},
+    themes::{ColorData, Theme, ThemeHandle, update_theme},
+};
+
+const PIXELS_PER_METER: f32 = 100.0;
+
+// seconds the banner stays on screen, the last second of it fading out
+const BANNER_DURATION: f32 = 3.0;
+
+#[derive(Resource, Default)]
+pub struct ZoneListHandle(pub Handle<ZoneList>);
+
+// used until the zone list asset is loaded
+#[derive(Resource)]
+pub struct BuiltinZoneList(pub ZoneList);
+
+/// The loaded zone list, or the built-in one while it is loading.
+#[derive(SystemParam)]
+pub struct DepthZones<'w> {
+    handle: Res<'w, ZoneListHandle>,
+    lists: Res<'w, Assets<ZoneList>>,
+    builtin: Res<'w, BuiltinZoneList>,
+}
+
+/// Depth bands the shaft is split into, from top to bottom.
+#[derive(Asset, Serialize, Deserialize, Clone, Debug, Reflect)]
+pub struct ZoneList {
+    // meters below the start of a zone over which the settings blend over from the zone above
+    #[serde(default = "default_blend")]
+    pub blend: f32,
+    pub zones: Vec<DepthZone>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Reflect)]
+pub struct DepthZone {
+    pub name: String,
+    // meters below the start of the run the zone begins at. zones have to be sorted by it.
+    pub depth: f32,
+    // multiplies the chance per tick to spawn an obstacle
+    #[serde(default = "default_one")]
+    pub spawn_rate: f64,
+    // multiplies the weight of obstacle kinds by name, kinds that are not listed keep their weight
+    #[serde(default)]
+    pub weights: HashMap<String, f32>,
+    // the theme decides everything below that is not set
+    #[serde(default)]
+    pub background_color: Option<ColorData>,
+    #[serde(default)]
+    pub walls_color: Option<ColorData>,
+    // paths are relative to themes/ like the ones of themes. an empty path turns the theme's off.
+    #[serde(default)]
+    pub walls_texture: Option<String>,
+    #[serde(default)]
+    pub music_path: Option<String>,
+}
+
+/// Settings at a depth, part way between the zone above and the zone the depth is in.
+#[derive(Clone, Copy, Debug)]
+pub struct ZoneBlend<'a> {
+    from: Option<&'a DepthZone>,
+    to: Option<&'a DepthZone>,
+    // 0.0 is all from, 1.0 all to
+    t: f32,
+}
+
+/// Index of the zone the torso is in.
+#[derive(Resource, Default)]
+pub struct CurrentZone(pub usize);
+
+/// Sent when the torso falls into a deeper zone.
+#[derive(Event)]
+pub struct ZoneEntered(pub usize);
+
+#[derive(Component)]
+pub struct ZoneBanner {
+    pub age: f32,
+}
+
+pub struct ZonePlugin {
+    // skips the banner and background when true
+    pub headless: bool,
+}
+
+impl Plugin for ZonePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(RonAssetPlugin::<ZoneList>::new(&["zones.ron"]))
+            .insert_resource(BuiltinZoneList(ZoneList::builtin()))
+            .init_resource::<CurrentZone>()
+            .add_event::<ZoneEntered>()
+            .add_systems(PreStartup, load_zone_list)
+            .add_systems(NewRun, reset_zone)
+            .add_systems(
+                FixedUpdate,
+                track_zone
+                    .after(PhysicsSet::Writeback)
+                    .after(recenter_world)
+                    .run_if(in_state(GameState::InGame)),
+            );
+
+        if !self.headless {
+            app.add_systems(Update, (show_zone_banner, fade_zone_banner).chain())
+                .add_systems(Update, blend_zone_background.after(update_theme));
+        }
+    }
+}
+
+pub fn load_zone_list(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(ZoneListHandle(asset_server.load("zones/default.zones.ron")));
+}
+
+/// Meters below the start of the run at the world y.
+pub fn depth_at(y: f32, world_shift: &WorldShift) -> f32 {
+    depth_at_run_y(y as f64 - world_shift.0)
+}
+
+/// Meters below the start of the run at a y counted from the start of the run, like the shaft uses.
+pub fn depth_at_run_y(run_y: f64) -> f32 {
+    ((PLAYER_START_Y as f64 - run_y) / PIXELS_PER_METER as f64) as f32
+}
+
+impl DepthZones<'_> {
+    pub fn get(&self) -> &ZoneList {
+        self.lists.get(&self.handle.0).unwrap_or(&self.builtin.0)
+    }
+}
+
+impl ZoneList {
+    /// Zone list compiled into the binary, available before any asset is loaded.
+    pub fn builtin() -> Self {
+        ron::from_str(include_str!("../assets/zones/default.zones.ron"))
+            .expect("built-in zone list is valid")
+    }
+
+    /// Index of the zone the depth is in. Depths above the first zone count as the first zone.
+    pub fn index_at(&self, depth: f32) -> usize {
+        self.zones
+            .iter()
+            .rposition(|zone| depth >= zone.depth)
+            .unwrap_or(0)
+    }
+
+    pub fn blend_at(&self, depth: f32) -> ZoneBlend<'_> {
+        let index = self.index_at(depth);
+        let to = self.zones.get(index);
+        let Some(from) = index.checked_sub(1).and_then(|above| self.zones.get(above)) else {
+            return ZoneBlend {
+                from: to,
+                to,
+                t: 1.0,
+            };
+        };
+
+        let t = match to {
+            Some(to) if self.blend > 0.0 => ((depth - to.depth) / self.blend).clamp(0.0, 1.0),
+            _ => 1.0,
+        };
+        ZoneBlend {
+            from: Some(from),
+            to,
+            t,
+        }
+    }
+}
+
+impl<'a> ZoneBlend<'a> {
+    pub fn spawn_rate(&self) -> f64 {
+        let rate = |zone: Option<&DepthZone>| zone.map_or(1.0, |zone| zone.spawn_rate);
+        rate(self.from) + (rate(self.to) - rate(self.from)) * self.t as f64
+    }
+
+    /// Factor for the weight of the obstacle kind.
+    pub fn weight(&self, kind_name: &str) -> f32 {
+        let weight = |zone: Option<&DepthZone>| {
+            zone.and_then(|zone| zone.weights.get(kind_name))
+                .copied()
+                .unwrap_or(1.0)
+        };
+        weight(self.from) + (weight(self.to) - weight(self.from)) * self.t
+    }
+
+    /// Blends a color the zones can set, `theme_color` is used where they do not.
+    pub fn color(
+        &self,
+        theme_color: Color,
+        field: impl Fn(&DepthZone) -> &Option<ColorData>,
+    ) -> Color {
+        let color = |zone: Option<&DepthZone>| {
+            zone.and_then(|zone| field(zone).as_ref())
+                .map_or(theme_color, |color| color.to_color())
+        };
+        color(self.from).mix(&color(self.to), self.t)
+    }
+
+    /// The zone that has more say at this depth. Used for settings that can not be blended.
+    pub fn nearest(&self) -> Option<&'a DepthZone> {
+        if self.t < 0.5 { self.from } else { self.to }
+    }
+}
+
+pub fn reset_zone(mut current_zone: ResMut<CurrentZone>) {
+    current_zone.0 = 0;
+}
+
+pub fn track_zone(
+    player_query: Query<&Transform, With<PlayerTorso>>,
+    world_shift: Res<WorldShift>,
+    zones: DepthZones,
+    mut current_zone: ResMut<CurrentZone>,
+    mut zone_events: EventWriter<ZoneEntered>,
+) {
+    let Ok(player_transform) = player_query.single() else {
+        return;
+    };
+    let zone_list = zones.get();
+    let index = zone_list.index_at(depth_at(player_transform.translation.y, &world_shift));
+
+    if index != current_zone.0 {
+        // bouncing back up over a boundary is not announced
+        if index > current_zone.0 {
+            zone_events.write(ZoneEntered(index));
+            if let Some(zone) = zone_list.zones.get(index) {
+                info!("Entered zone '{}'.", zone.name);
+            }
+        }
+        current_zone.0 = index;
+    }
+}
+
+/// Blends the background over to the color of the zone the torso is in.
+pub fn blend_zone_background(
+    mut camera_query: Query<&mut Camera>,
+    player_query: Query<&Transform, With<PlayerTorso>>,
+    world_shift: Res<WorldShift>,
+    zones: DepthZones,
+    theme_handle: Res<ThemeHandle>,
+    themes: Res<Assets<Theme>>,
+) {
+    let (Some(theme), Ok(player_transform)) = (themes.get(&theme_handle.0), player_query.single())
+    else {
+        return;
+    };
+
+    let blend = zones
+        .get()
+        .blend_at(depth_at(player_transform.translation.y, &world_shift));
+    let color = blend.color(theme.background_color.to_color(), |zone| {
+        &zone.background_color
+    });
+    for mut camera in camera_query.iter_mut() {
+        if !matches!(camera.clear_color, ClearColorConfig::Custom(current) if current == color) {
+            camera.clear_color = ClearColorConfig::Custom(color);
+        }
+    }
+}
+
+pub fn show_zone_banner(
+    mut commands: Commands,
+    mut zone_events: EventReader<ZoneEntered>,
+    banner_query: Query<Entity, With<ZoneBanner>>,
+    zones: DepthZones,
+) {
+    let Some(ZoneEntered(index)) = zone_events.read().last() else {
+        return;
+    };
+    let Some(zone) = zones.get().zones.get(*index) else {
+        return;
+    };
+
+    for banner in banner_query.iter() {
+        commands.entity(banner).despawn();
+    }
+
+    commands.spawn((
+        Text::new(zone.name.clone()),
+        TextFont {
+            font_size: 48.0,
+            ..default()
+        },
+        TextColor(Color::srgb(1.0, 1.0, 1.0)),
+        TextLayout::new_with_justify(JustifyText::Center),
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            top: Val::Percent(25.0),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        ZoneBanner { age: 0.0 },
+    ));
+}
+
+pub fn fade_zone_banner(
+    mut commands: Commands,
+    mut banner_query: Query<(Entity, &mut ZoneBanner, &mut TextColor)>,
+    time: Res<Time>,
+) {
+    for (entity, mut banner, mut text_color) in banner_query.iter_mut() {
+        banner.age += time.delta_secs();
+        if banner.age >= BANNER_DURATION {
+            commands.entity(entity).despawn();
+        } else {
+            text_color
+                .0
+                .set_alpha((BANNER_DURATION - banner.age).min(1.0));
+        }
+    }
+}
+
+fn default_blend() -> f32 {
+    50.0
+}
+
+fn default_one() -> f64 {
+    1.0
+}